@@ -1,3 +0,0 @@
-fn main() {
-    advent_of_code_2020::day22::main();
-}
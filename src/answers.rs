@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Day;
+
+/// Expected answers for each day and part, as recorded in the answers file.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Answers(BTreeMap<(usize, usize), String>);
+
+type Entry = ((usize, usize), String);
+
+// Line `<day> <part> <answer>`, blank lines and `#` comments are skipped.
+fn entry(line: &str) -> Result<Option<Entry>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let mut it = line.splitn(3, ' ');
+    match (it.next(), it.next(), it.next().map(|s| s.trim())) {
+        (Some(day), Some(part), Some(answer)) if !answer.is_empty() => {
+            let day: usize = number(day)?;
+            let part: usize = number(part)?;
+            if part != 1 && part != 2 {
+                return Err(Error::new(line, "part must be 1 or 2"));
+            }
+            Ok(Some(((day, part), answer.to_string())))
+        },
+        _ => Err(Error::new(line, "expected `<day> <part> <answer>`"))
+    }
+}
+
+impl Answers {
+    pub fn parse(text: &str) -> Result<Self> {
+        let entries = parse_lines(text.lines(), entry)?;
+
+        let mut answers = BTreeMap::new();
+        for (i, e) in entries.into_iter().enumerate() {
+            if let Some((key, answer)) = e {
+                if answers.insert(key, answer).is_some() {
+                    let line = text.lines().nth(i).unwrap_or_default();
+                    return Err(Error::new(line, "duplicate answer").at(i + 1));
+                }
+            }
+        }
+        Ok(Self(answers))
+    }
+
+    /// Expected answer for given day and part, if one is recorded.
+    pub fn get(&self, day: usize, part: usize) -> Option<&str> {
+        self.0.get(&(day, part)).map(|s| s.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Result of checking one part against its recorded answer.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    Missing(String),
+}
+
+/// Solve every part of the day for given input text (if any) and compare with recorded answers.
+pub fn check(day: &Day, text: Option<&str>, answers: &Answers) -> Vec<(usize, Outcome)> {
+    let parts = 1..=day.parts();
+    let text = match text {
+        Some(text) => text,
+        None => return parts.map(|p| (p, Outcome::Missing("no input".to_string()))).collect(),
+    };
+    let input = match day.parse(text) {
+        Ok(input) => input,
+        Err(e) => return parts.map(|p| (p, Outcome::Fail(e.to_string()))).collect(),
+    };
+
+    parts
+        .map(|p| {
+            let outcome = match answers.get(day.number(), p) {
+                None => Outcome::Missing("no answer".to_string()),
+                Some(expected) => {
+                    let answer = day.solve(p, &input).unwrap();
+                    if answer == expected {
+                        Outcome::Pass
+                    } else {
+                        Outcome::Fail(format!("expected {}, got {}", expected, answer))
+                    }
+                },
+            };
+            (p, outcome)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "# day part answer\n\n01 1 514579\n21 2 mxmxvkd,sqjhc,fvjkl\n";
+        let answers = Answers::parse(text).unwrap();
+
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers.get(1, 1), Some("514579"));
+        assert_eq!(answers.get(21, 2), Some("mxmxvkd,sqjhc,fvjkl"));
+        assert_eq!(answers.get(1, 2), None);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let e = Answers::parse("01 1 514579\n01 3 42\n").unwrap_err();
+        assert_eq!(e.line(), Some(2));
+        assert_eq!(e.reason(), "part must be 1 or 2");
+
+        let e = Answers::parse("01 1 514579\n01 1 42\n").unwrap_err();
+        assert_eq!(e.line(), Some(2));
+        assert_eq!(e.reason(), "duplicate answer");
+
+        assert!(Answers::parse("01 1\n").is_err());
+        assert!(Answers::parse("x 1 42\n").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let day = crate::day(1).unwrap();
+        let answers = Answers::parse("01 1 514579\n").unwrap();
+        let text = "1721\n979\n366\n299\n675\n1456\n";
+
+        assert_eq!(check(&day, Some(text), &answers), vec![
+            (1, Outcome::Pass),
+            (2, Outcome::Missing("no answer".to_string())),
+        ]);
+
+        let answers = Answers::parse("01 1 42\n01 2 241861950\n").unwrap();
+        assert_eq!(check(&day, Some(text), &answers), vec![
+            (1, Outcome::Fail("expected 42, got 514579".to_string())),
+            (2, Outcome::Pass),
+        ]);
+
+        assert_eq!(check(&day, None, &answers), vec![
+            (1, Outcome::Missing("no input".to_string())),
+            (2, Outcome::Missing("no input".to_string())),
+        ]);
+        assert!(matches!(check(&day, Some("x"), &answers)[0].1, Outcome::Fail(_)));
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::mem;
+
+use crate::error::{Error, Result};
+use crate::grid::{Grid, Pos};
+
+/// Neighbourhood of the cell. It has to be symmetric (cells are neighbours of their neighbours)
+/// and must depend only on fixed cells, as it is computed just once for each cell.
+pub type Peers = fn(&Grid<char>, Pos) -> Vec<Pos>;
+
+/// Characters of dead and live cells, any other character is a fixed cell that never changes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Alphabet {
+    pub dead: char,
+    pub alive: char,
+}
+
+/// Numbers of live neighbours for a dead cell to come alive (birth), and for a live cell to stay
+/// alive (survival).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Rule {
+    birth: u64,
+    survival: u64,
+}
+
+impl Rule {
+    pub fn new(birth: &[usize], survival: &[usize]) -> Self {
+        let mask = |counts: &[usize]| counts.iter().fold(0u64, |acc, n| {
+            assert!(*n < 64, "too many neighbours: {}", n);
+            acc | 1 << n
+        });
+        Self { birth: mask(birth), survival: mask(survival) }
+    }
+
+    /// Rule in the `B<counts>/S<counts>` notation, e.g. `B3/S23` for Conway's life.
+    pub fn parse(text: &str) -> Result<Self> {
+        let counts = |part: &str, prefix: char| -> Result<Vec<usize>> {
+            let digits = part.strip_prefix(prefix)
+                .ok_or_else(|| Error::new(text, format!("expected `{}` followed by counts", prefix)))?;
+            digits.chars()
+                .map(|c| c.to_digit(10).map(|d| d as usize).ok_or_else(|| Error::new(text, format!("invalid count {:?}", c))))
+                .collect()
+        };
+        let (birth, survival) = text.split_once('/')
+            .ok_or_else(|| Error::new(text, "expected `B<counts>/S<counts>`"))?;
+        Ok(Self::new(&counts(birth, 'B')?, &counts(survival, 'S')?))
+    }
+
+    pub fn next(&self, alive: bool, count: usize) -> bool {
+        let counts = if alive { self.survival } else { self.birth };
+        count < 64 && counts & 1 << count != 0
+    }
+}
+
+/// How the run ended.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Outcome {
+    /// Nothing changes after the generation.
+    Fixed(usize),
+    /// Generation `start + period` repeats generation `start`.
+    Cycle { start: usize, period: usize },
+    /// Limit of generations reached.
+    Limit,
+}
+
+/// Neighbourhoods of all the cells as compressed sparse rows: neighbours of the cell with index `i`
+/// (counting row by row) are `targets[offsets[i]..offsets[i + 1]]`, also as indices. Fixed cells
+/// have no neighbours and are nobody's neighbour, as they never change.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Neighbours {
+    offsets: Vec<u32>,
+    targets: Vec<u32>,
+}
+
+impl Neighbours {
+    pub fn new(grid: &Grid<char>, alphabet: Alphabet, peers: Peers) -> Self {
+        let index = |(row, col): Pos| (row * grid.cols() + col) as u32;
+        let open = |pos: Pos| grid[pos] == alphabet.dead || grid[pos] == alphabet.alive;
+        let mut offsets = Vec::with_capacity(grid.rows() * grid.cols() + 1);
+        let mut targets = Vec::new();
+        offsets.push(0);
+        for pos in grid.positions() {
+            if open(pos) {
+                targets.extend(peers(grid, pos).into_iter().filter(|peer| open(*peer)).map(index));
+            }
+            offsets.push(targets.len() as u32);
+        }
+        Self { offsets, targets }
+    }
+
+    pub fn of(&self, i: usize) -> &[u32] {
+        &self.targets[self.offsets[i] as usize..self.offsets[i + 1] as usize]
+    }
+}
+
+// Random key of the cell for hashing the set of live cells (splitmix64 of its index).
+fn key(index: usize) -> u64 {
+    let mut z = (index as u64).wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Cellular automaton on a grid of characters. Neighbourhoods are computed once, the number of live
+/// neighbours of every cell is kept up to date, and only cells that changed or whose neighbours did in
+/// the last generation are re-evaluated. The next generation is written into the back buffer, which
+/// is then swapped with the front one; only cells that changed are written, so each buffer is updated
+/// in place.
+pub struct Automaton {
+    rule: Rule,
+    alphabet: Alphabet,
+    neighbours: Neighbours,
+    front: Grid<char>,
+    back: Grid<char>,
+    counts: Grid<usize>,
+    // Cells changed by the last generation, and cells to evaluate for the next one.
+    changed: Vec<Pos>,
+    candidates: Vec<Pos>,
+    // Generation in which each cell last became a candidate, to add it only once.
+    marked: Grid<usize>,
+    generation: usize,
+    // Hash of the live cells, and the live cells (as indices) of each generation seen so far by
+    // their hash, to confirm a repeat when the hashes match.
+    hash: u64,
+    history: HashMap<u64, Vec<(Vec<u32>, usize)>>,
+    // Earlier generation the current one repeats.
+    repeats: Option<usize>,
+}
+
+impl Automaton {
+    pub fn new(grid: Grid<char>, rule: Rule, alphabet: Alphabet, peers: Peers) -> Self {
+        let neighbours = Neighbours::new(&grid, alphabet, peers);
+        let pos = |i: u32| (i as usize / grid.cols(), i as usize % grid.cols());
+        let mut counts = Grid::filled(grid.rows(), grid.cols(), 0);
+        let mut hash = 0;
+        for (i, cell) in grid.positions().enumerate() {
+            if grid[cell] == alphabet.alive {
+                hash ^= key(i);
+                for peer in neighbours.of(i) {
+                    counts[pos(*peer)] += 1;
+                }
+            }
+        }
+        let candidates = grid.positions()
+            .filter(|pos| grid[*pos] == alphabet.dead || grid[*pos] == alphabet.alive)
+            .collect();
+
+        let mut automaton = Self {
+            rule,
+            alphabet,
+            neighbours,
+            back: grid.clone(),
+            marked: Grid::filled(grid.rows(), grid.cols(), 0),
+            front: grid,
+            counts,
+            changed: Vec::new(),
+            candidates,
+            generation: 0,
+            hash,
+            history: HashMap::new(),
+            repeats: None,
+        };
+        automaton.record();
+        automaton
+    }
+
+    pub fn grid(&self) -> &Grid<char> {
+        &self.front
+    }
+
+    /// Number of live neighbours of each cell, 0 for fixed cells.
+    pub fn counts(&self) -> &Grid<usize> {
+        &self.counts
+    }
+
+    /// Number of generations that changed anything.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Cells changed by the last generation.
+    pub fn changed(&self) -> &[Pos] {
+        &self.changed
+    }
+
+    pub fn alive(&self) -> usize {
+        self.front.iter().filter(|c| **c == self.alphabet.alive).count()
+    }
+
+    /// Earlier generation the current one repeats, if any.
+    pub fn repeats(&self) -> Option<usize> {
+        self.repeats
+    }
+
+    // Add the current generation to the history, unless it repeats an earlier one.
+    fn record(&mut self) {
+        let live: Vec<u32> = self.front.iter().enumerate()
+            .filter(|(_, c)| **c == self.alphabet.alive)
+            .map(|(i, _)| i as u32)
+            .collect();
+        let seen = self.history.entry(self.hash).or_default();
+        self.repeats = seen.iter().find(|(cells, _)| *cells == live).map(|(_, generation)| *generation);
+        if self.repeats.is_none() {
+            seen.push((live, self.generation));
+        }
+    }
+
+    /// Compute the next generation, returns the number of changed cells.
+    pub fn step(&mut self) -> usize {
+        // Bring the back buffer up to date with the front one.
+        for pos in &self.changed {
+            self.back[*pos] = self.front[*pos];
+        }
+
+        let mut changed = Vec::new();
+        for pos in mem::take(&mut self.candidates) {
+            let cell = self.front[pos];
+            let alive = self.rule.next(cell == self.alphabet.alive, self.counts[pos]);
+            let next = if alive { self.alphabet.alive } else { self.alphabet.dead };
+            if next != cell {
+                self.back[pos] = next;
+                changed.push(pos);
+            }
+        }
+        mem::swap(&mut self.front, &mut self.back);
+        if changed.is_empty() {
+            self.changed = changed;
+            return 0;
+        }
+
+        self.generation += 1;
+        let generation = self.generation;
+        let mut candidates = Vec::new();
+        let mut mark = |pos: Pos, marked: &mut Grid<usize>| {
+            if marked[pos] != generation {
+                marked[pos] = generation;
+                candidates.push(pos);
+            }
+        };
+        let cols = self.front.cols();
+        for pos in &changed {
+            let alive = self.front[*pos] == self.alphabet.alive;
+            let i = pos.0 * cols + pos.1;
+            self.hash ^= key(i);
+            mark(*pos, &mut self.marked);
+            for peer in self.neighbours.of(i) {
+                let peer = (*peer as usize / cols, *peer as usize % cols);
+                if alive {
+                    self.counts[peer] += 1;
+                } else {
+                    self.counts[peer] -= 1;
+                }
+                if self.front[peer] == self.alphabet.dead || self.front[peer] == self.alphabet.alive {
+                    mark(peer, &mut self.marked);
+                }
+            }
+        }
+        self.candidates = candidates;
+        self.changed = changed;
+        self.record();
+        self.changed.len()
+    }
+
+    /// Step until nothing changes, a generation repeats an earlier one (looked up by a hash of the
+    /// live cells, then compared cell by cell), or `limit` generations are computed.
+    pub fn run(&mut self, limit: usize) -> Outcome {
+        for _ in 0..limit {
+            if self.step() == 0 {
+                return Outcome::Fixed(self.generation);
+            }
+            if let Some(start) = self.repeats {
+                return Outcome::Cycle { start, period: self.generation - start };
+            }
+        }
+        Outcome::Limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIFE: Alphabet = Alphabet { dead: '.', alive: '#' };
+
+    fn adjacent(grid: &Grid<char>, pos: Pos) -> Vec<Pos> {
+        grid.neighbours8(pos).collect()
+    }
+
+    fn life(rows: Vec<&str>) -> Automaton {
+        Automaton::new(Grid::parse(rows, Some).unwrap(), Rule::parse("B3/S23").unwrap(), LIFE, adjacent)
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let grid = Grid::parse(vec!["#.#", "..#"], Some).unwrap();
+        let neighbours = Neighbours::new(&grid, LIFE, adjacent);
+        assert_eq!(neighbours.of(0), &[1, 3, 4]);
+        assert_eq!(neighbours.of(4), &[0, 1, 2, 3, 5]);
+
+        let grid = Grid::parse(vec!["#x#", "xx#"], Some).unwrap();
+        let neighbours = Neighbours::new(&grid, LIFE, adjacent);
+        assert_eq!(neighbours.of(0), &[]);
+        assert_eq!(neighbours.of(1), &[]);
+        assert_eq!(neighbours.of(2), &[5]);
+        assert_eq!(neighbours.of(5), &[2]);
+    }
+
+    #[test]
+    fn test_peers_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        fn counted(grid: &Grid<char>, pos: Pos) -> Vec<Pos> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            adjacent(grid, pos)
+        }
+
+        let grid = Grid::parse(vec![".....", "..#..", "..#..", "..#..", "....x"], Some).unwrap();
+        let mut automaton = Automaton::new(grid, Rule::parse("B3/S23").unwrap(), LIFE, counted);
+        assert_eq!(automaton.run(10), Outcome::Cycle { start: 0, period: 2 });
+        assert_eq!(CALLS.load(Ordering::SeqCst), 24);
+    }
+
+    #[test]
+    fn test_rule() {
+        let rule = Rule::parse("B3/S23").unwrap();
+        assert_eq!(rule, Rule::new(&[3], &[2, 3]));
+        assert!(rule.next(false, 3));
+        assert!(!rule.next(false, 2));
+        assert!(rule.next(true, 2));
+        assert!(!rule.next(true, 4));
+        assert!(!rule.next(true, 100));
+
+        assert_eq!(Rule::parse("B/S").unwrap(), Rule::new(&[], &[]));
+        assert!(Rule::parse("B3S23").is_err());
+        assert!(Rule::parse("S23/B3").is_err());
+        assert!(Rule::parse("B3/Sx").is_err());
+    }
+
+    #[test]
+    fn test_fixed() {
+        let mut automaton = life(vec![
+            "....",
+            ".##.",
+            ".#..",
+            "....",
+        ]);
+        assert_eq!(automaton.run(10), Outcome::Fixed(1));
+        assert_eq!(automaton.grid().to_string(), "....\n.##.\n.##.\n....");
+        assert_eq!(automaton.changed(), &[]);
+        assert_eq!(automaton.alive(), 4);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut automaton = life(vec![
+            ".....",
+            "..#..",
+            "..#..",
+            "..#..",
+            ".....",
+        ]);
+        assert_eq!(automaton.step(), 4);
+        assert_eq!(automaton.changed(), &[(1, 2), (2, 1), (2, 3), (3, 2)]);
+        assert_eq!(automaton.counts()[(1, 2)], 3);
+        assert_eq!(automaton.run(10), Outcome::Cycle { start: 0, period: 2 });
+        assert_eq!(automaton.grid().to_string(), ".....\n..#..\n..#..\n..#..\n.....");
+
+        let mut automaton = life(vec!["#"]);
+        assert_eq!(automaton.run(10), Outcome::Fixed(1));
+        assert_eq!(automaton.run(10), Outcome::Fixed(1));
+    }
+
+    #[test]
+    fn test_repeats() {
+        let blinker = vec![".....", "..#..", "..#..", "..#..", "....."];
+
+        // Generations made by single steps count as well.
+        let mut automaton = life(blinker.clone());
+        automaton.step();
+        assert_eq!(automaton.repeats(), None);
+        automaton.step();
+        assert_eq!(automaton.repeats(), Some(0));
+        assert_eq!(automaton.run(10), Outcome::Cycle { start: 1, period: 2 });
+
+        // Same hash as the next generation, but different cells, is not a repeat.
+        let mut next = life(blinker.clone());
+        next.step();
+        let mut automaton = life(blinker);
+        automaton.history.insert(next.hash, vec![(vec![0], 0)]);
+        automaton.step();
+        assert_eq!(automaton.repeats(), None);
+        assert_eq!(automaton.run(10), Outcome::Cycle { start: 0, period: 2 });
+    }
+
+    #[test]
+    fn test_limit() {
+        // Glider never repeats itself on an infinite grid, but here it hits the edge.
+        let mut automaton = life(vec![
+            ".#......",
+            "..#.....",
+            "###.....",
+            "........",
+            "........",
+            "........",
+        ]);
+        assert_eq!(automaton.run(4), Outcome::Limit);
+        assert_eq!(automaton.generation(), 4);
+        assert_eq!(automaton.grid().to_string(), "........\n..#.....\n...#....\n.###....\n........\n........");
+        assert_eq!(automaton.run(100), Outcome::Fixed(15));
+        assert_eq!(automaton.grid().row(5), &['.', '.', '.', '.', '#', '#', '.', '.']);
+    }
+}
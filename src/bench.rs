@@ -0,0 +1,196 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+use crate::json::{self, Value};
+use crate::solution::Day;
+
+/// Timings of one stage of the day (`parse`, `part1` or `part2`) over several iterations.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Timing {
+    pub day: usize,
+    pub stage: String,
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+impl Timing {
+    fn of(day: usize, stage: &str, mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        Self {
+            day,
+            stage: stage.to_string(),
+            min: samples[0],
+            median: samples[samples.len() / 2],
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+fn sample<T, F: FnMut() -> T>(iterations: usize, mut f: F) -> Vec<Duration> {
+    (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            let result = f();
+            let elapsed = start.elapsed();
+            drop(result);
+            elapsed
+        })
+        .collect()
+}
+
+/// Run parse and every part of the day separately for given number of iterations.
+pub fn bench(day: &Day, text: &str, iterations: usize) -> Result<Vec<Timing>> {
+    assert!(iterations > 0);
+    let input = day.parse(text)?;
+
+    let mut timings = Vec::with_capacity(day.parts() + 1);
+    timings.push(Timing::of(day.number(), "parse", sample(iterations, || day.parse(text))));
+    for part in 1..=day.parts() {
+        let samples = sample(iterations, || day.solve(part, &input));
+        timings.push(Timing::of(day.number(), &format!("part{}", part), samples));
+    }
+    Ok(timings)
+}
+
+pub fn to_json(timings: &[Timing]) -> String {
+    let rows = timings.iter()
+        .map(|t| format!("  {{\"day\": {}, \"stage\": {}, \"min_ns\": {}, \"median_ns\": {}, \"max_ns\": {}}}",
+                         t.day, json::quote(&t.stage), t.min.as_nanos(), t.median.as_nanos(), t.max.as_nanos()))
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+fn from_object(object: &json::Object) -> Result<Timing> {
+    let number = |key: &str| match object.get(key) {
+        Some(Value::Number(n)) => Ok(*n),
+        _ => Err(Error::new(key, "expected a number")),
+    };
+    let stage = match object.get("stage") {
+        Some(Value::String(s)) => s.to_owned(),
+        _ => return Err(Error::new("stage", "expected a string")),
+    };
+
+    Ok(Timing {
+        day: number("day")? as usize,
+        stage,
+        min: Duration::from_nanos(number("min_ns")?),
+        median: Duration::from_nanos(number("median_ns")?),
+        max: Duration::from_nanos(number("max_ns")?),
+    })
+}
+
+pub fn from_json(text: &str) -> Result<Vec<Timing>> {
+    json::parse_objects(text)?
+        .iter()
+        .enumerate()
+        .map(|(i, object)| from_object(object)
+            .map_err(|e| Error::new(e.text(), format!("entry {}: {}", i + 1, e.reason()))))
+        .collect()
+}
+
+/// Stage with median time above the baseline one by more than the threshold.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Regression {
+    pub day: usize,
+    pub stage: String,
+    pub baseline: Duration,
+    pub current: Duration,
+}
+
+impl Regression {
+    /// Slowdown relative to the baseline, in percent.
+    pub fn percent(&self) -> f64 {
+        percent(self.baseline, self.current)
+    }
+}
+
+fn percent(baseline: Duration, current: Duration) -> f64 {
+    (current.as_secs_f64() / baseline.as_secs_f64() - 1.0) * 100.0
+}
+
+/// Compare medians with the baseline, `threshold` is allowed slowdown in percent.
+pub fn compare(current: &[Timing], baseline: &[Timing], threshold: f64) -> Vec<Regression> {
+    current.iter()
+        .filter_map(|t| {
+            baseline.iter()
+                .find(|b| b.day == t.day && b.stage == t.stage)
+                .filter(|b| b.median > Duration::from_nanos(0))
+                .filter(|b| percent(b.median, t.median) > threshold)
+                .map(|b| Regression {
+                    day: t.day,
+                    stage: t.stage.clone(),
+                    baseline: b.median,
+                    current: t.median,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day01::Day01;
+
+    fn timing(day: usize, stage: &str, median: u64) -> Timing {
+        Timing {
+            day,
+            stage: stage.to_string(),
+            min: Duration::from_nanos(median / 2),
+            median: Duration::from_nanos(median),
+            max: Duration::from_nanos(median * 2),
+        }
+    }
+
+    #[test]
+    fn test_timing() {
+        let samples = [5, 1, 4, 2, 3].iter().map(|n| Duration::from_nanos(*n)).collect();
+        let t = Timing::of(1, "parse", samples);
+        assert_eq!(t.min, Duration::from_nanos(1));
+        assert_eq!(t.median, Duration::from_nanos(3));
+        assert_eq!(t.max, Duration::from_nanos(5));
+    }
+
+    #[test]
+    fn test_bench() {
+        let day = Day::of::<Day01>(1);
+        let timings = bench(&day, "1721\n979\n366\n299\n675\n1456\n", 3).unwrap();
+
+        let stages = timings.iter().map(|t| t.stage.as_str()).collect::<Vec<_>>();
+        assert_eq!(stages, vec!["parse", "part1", "part2"]);
+        assert!(timings.iter().all(|t| t.day == 1 && t.min <= t.median && t.median <= t.max));
+
+        assert!(bench(&day, "x", 3).is_err());
+    }
+
+    #[test]
+    fn test_json() {
+        let timings = vec![timing(1, "parse", 100), timing(15, "part2", 1_000_000_000)];
+        assert_eq!(from_json(&to_json(&timings)), Ok(timings));
+        assert_eq!(from_json("[]"), Ok(vec![]));
+
+        let e = from_json("[{\"day\": 1, \"stage\": \"parse\", \"min_ns\": 1, \"max_ns\": 2}]").unwrap_err();
+        assert_eq!(e.to_string(), "entry 1: expected a number in \"median_ns\"");
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = vec![timing(1, "parse", 100), timing(1, "part1", 100), timing(1, "part2", 0)];
+        let current = vec![
+            timing(1, "parse", 105),
+            timing(1, "part1", 150),
+            timing(1, "part2", 10),
+            timing(2, "parse", 1000),
+        ];
+
+        let regressions = compare(&current, &baseline, 10.0);
+        assert_eq!(regressions, vec![Regression {
+            day: 1,
+            stage: "part1".to_string(),
+            baseline: Duration::from_nanos(100),
+            current: Duration::from_nanos(150),
+        }]);
+        assert_eq!(regressions[0].percent().round(), 50.0);
+        assert_eq!(compare(&current, &baseline, 60.0), vec![]);
+    }
+}
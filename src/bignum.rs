@@ -0,0 +1,216 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, SubAssign};
+
+/// Unsigned integer of any size, with just enough arithmetic for counting (and picking one of)
+/// combinations: addition, subtraction, comparison and decimal formatting.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct BigUint {
+    // Little-endian base 2^32 digits, without leading zeros (so zero has none).
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Number of significant bits.
+    pub fn bits(&self) -> usize {
+        match self.digits.last() {
+            Some(top) => 32 * self.digits.len() - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.digits[..] {
+            [] => Some(0),
+            [a] => Some(a as u64),
+            [a, b] => Some(a as u64 | (b as u64) << 32),
+            _ => None,
+        }
+    }
+
+    fn trim(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+
+    // Divide in place by a small divisor, returns the remainder.
+    fn div_rem(&mut self, divisor: u32) -> u32 {
+        let mut rem = 0u64;
+        for digit in self.digits.iter_mut().rev() {
+            let acc = rem << 32 | *digit as u64;
+            *digit = (acc / divisor as u64) as u32;
+            rem = acc % divisor as u64;
+        }
+        self.trim();
+        rem as u32
+    }
+
+    /// Uniformly random number below `self` (which must not be zero), taking random bits from `next`.
+    pub fn random_below<F: FnMut() -> u64>(&self, mut next: F) -> Self {
+        assert!(!self.is_zero(), "no number below zero");
+        let bits = self.bits();
+        loop {
+            let mut digits: Vec<u32> = (0..self.digits.len()).map(|_| next() as u32).collect();
+            if !bits.is_multiple_of(32) {
+                *digits.last_mut().unwrap() &= (1 << (bits % 32)) - 1;
+            }
+            let mut n = Self { digits };
+            n.trim();
+            if n < *self {
+                return n;
+            }
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        let mut n = Self { digits: vec![n as u32, (n >> 32) as u32] };
+        n.trim();
+        n
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.digits.len().cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        if self.digits.len() < other.digits.len() {
+            self.digits.resize(other.digits.len(), 0);
+        }
+        let mut carry = 0u64;
+        for (i, digit) in self.digits.iter_mut().enumerate() {
+            let acc = *digit as u64 + other.digits.get(i).copied().unwrap_or(0) as u64 + carry;
+            *digit = acc as u32;
+            carry = acc >> 32;
+        }
+        if carry > 0 {
+            self.digits.push(carry as u32);
+        }
+    }
+}
+
+impl Add<&BigUint> for BigUint {
+    type Output = BigUint;
+
+    fn add(mut self, other: &BigUint) -> BigUint {
+        self += other;
+        self
+    }
+}
+
+impl SubAssign<&BigUint> for BigUint {
+    fn sub_assign(&mut self, other: &BigUint) {
+        assert!(*self >= *other, "subtraction overflow");
+        let mut borrow = 0i64;
+        for (i, digit) in self.digits.iter_mut().enumerate() {
+            let mut acc = *digit as i64 - other.digits.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = 0;
+            if acc < 0 {
+                acc += 1 << 32;
+                borrow = 1;
+            }
+            *digit = acc as u32;
+        }
+        self.trim();
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Decimal digits in groups of 9, least significant first.
+        let mut n = self.clone();
+        let mut groups = Vec::new();
+        while !n.is_zero() {
+            groups.push(n.div_rem(1_000_000_000));
+        }
+        match groups.split_last() {
+            None => f.pad("0"),
+            Some((top, rest)) => {
+                let mut s = top.to_string();
+                for group in rest.iter().rev() {
+                    s.push_str(&format!("{:09}", group));
+                }
+                f.pad(&s)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut n = big(u64::MAX);
+        n += &big(1);
+        assert_eq!(n.to_string(), "18446744073709551616");
+        assert_eq!((n.bits(), n.to_u64()), (65, None));
+
+        n -= &big(2);
+        assert_eq!(n.to_u64(), Some(u64::MAX - 1));
+        n -= &n.clone();
+        assert!(n.is_zero());
+        assert_eq!(n.to_string(), "0");
+
+        // 2^128 = 340282366920938463463374607431768211456
+        let mut m = big(1);
+        for _ in 0..128 {
+            m = m.clone() + &m;
+        }
+        assert_eq!(m.to_string(), "340282366920938463463374607431768211456");
+        assert_eq!(format!("{:>5}", big(42)), "   42");
+    }
+
+    #[test]
+    fn test_cmp() {
+        assert!(big(0) < big(1));
+        assert!(big(1 << 40) > big(u32::MAX as u64));
+        assert!(big(5) + &big(u64::MAX) > big(u64::MAX));
+        assert_eq!(big(7).cmp(&big(7)), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_random_below() {
+        let mut state = 1u64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            state >> 11
+        };
+        let n = big(u64::MAX) + &big(u64::MAX);
+        for _ in 0..100 {
+            assert!(n.random_below(&mut next) < n);
+        }
+
+        let mut seen = [false; 5];
+        for _ in 0..100 {
+            seen[big(5).random_below(&mut next).to_u64().unwrap() as usize] = true;
+        }
+        assert_eq!(seen, [true; 5]);
+    }
+}
@@ -0,0 +1,608 @@
+use std::fs;
+use std::io;
+use std::process;
+use std::time::{Duration, Instant};
+
+use advent_of_code_2020::answers::{self, Answers, Outcome};
+use advent_of_code_2020::bench::{self, Timing};
+use advent_of_code_2020::day06::{Day06, Expr};
+use advent_of_code_2020::day08::{self, Day08, Debugger};
+use advent_of_code_2020::input::Input;
+use advent_of_code_2020::runner::{self, Answer, Job};
+use advent_of_code_2020::solution::Solution;
+
+const USAGE: &str = "\
+Usage:
+    aoc list
+    aoc run <day> [--part 1|2] [--input <path>] [--format text|json]
+    aoc run --all [--jobs <n>] [--format text|json]
+    aoc verify [--answers <path>]
+    aoc survey <query> [--input <path>]
+    aoc debug <script> [--input <path>]
+    aoc bench <day>|--all [--iterations <n>] [--save <path>] [--baseline <path>] [--threshold <percent>]
+
+Input defaults to inputs/dayNN.txt, use `--input -` to read it from stdin.
+With --jobs, days and their parts are solved on n worker threads.
+Answers default to inputs/answers.txt.
+Survey counts day 6 questions matching the query over all groups, e.g. 'count(exactly 2 people)'.
+Debug runs the day 8 program under the debugger, one command of the script file per line:
+break <op>|acc=<n>, watch <expr>, step, back, cont [<steps>], trace, loop.
+Bench runs 10 iterations by default, and flags medians over 10% slower than the baseline.";
+
+const ANSWERS: &str = "inputs/answers.txt";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Command {
+    List,
+    Run {
+        days: Vec<usize>,
+        part: Option<usize>,
+        input: Option<String>,
+        format: Format,
+        jobs: Option<usize>,
+    },
+    Verify {
+        answers: String,
+    },
+    Survey {
+        query: String,
+        input: Option<String>,
+    },
+    Debug {
+        script: String,
+        input: Option<String>,
+    },
+    Bench {
+        days: Vec<usize>,
+        iterations: usize,
+        save: Option<String>,
+        baseline: Option<String>,
+        threshold: u32,
+    },
+}
+
+fn parse_day(arg: &str) -> Result<usize, String> {
+    arg.parse::<usize>().ok()
+        .filter(|day| advent_of_code_2020::day(*day).is_some())
+        .ok_or_else(|| format!("unknown day: {}", arg))
+}
+
+fn all_days() -> Vec<usize> {
+    advent_of_code_2020::days().iter().map(|day| day.number()).collect()
+}
+
+fn parse_number<T: std::str::FromStr>(arg: Option<&String>, flag: &str) -> Result<T, String> {
+    arg.and_then(|n| n.parse::<T>().ok())
+        .ok_or_else(|| format!("{} expects a number", flag))
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let mut it = args.iter();
+    match it.next().map(|s| s.as_str()) {
+        Some("list") => {
+            if let Some(arg) = it.next() {
+                return Err(format!("unexpected argument: {}", arg));
+            }
+            Ok(Command::List)
+        },
+        Some("run") => {
+            let mut days = Vec::new();
+            let mut all = false;
+            let mut part = None;
+            let mut input = None;
+            let mut format = Format::Text;
+            let mut jobs = None;
+
+            while let Some(arg) = it.next() {
+                match arg.as_str() {
+                    "--all" => all = true,
+                    "--part" => {
+                        let p = it.next()
+                            .and_then(|p| p.parse::<usize>().ok())
+                            .filter(|p| *p == 1 || *p == 2)
+                            .ok_or("--part expects 1 or 2")?;
+                        part = Some(p);
+                    },
+                    "--input" => {
+                        let path = it.next().ok_or("--input expects a path")?;
+                        input = Some(path.to_owned());
+                    },
+                    "--jobs" => {
+                        let n = parse_number(it.next(), arg)?;
+                        if n == 0 {
+                            return Err("--jobs must be positive".to_string());
+                        }
+                        jobs = Some(n);
+                    },
+                    "--format" => {
+                        format = match it.next().map(|f| f.as_str()) {
+                            Some("text") => Format::Text,
+                            Some("json") => Format::Json,
+                            _ => return Err("--format expects text or json".to_string()),
+                        };
+                    },
+                    day if days.is_empty() && !day.starts_with("--") => days.push(parse_day(day)?),
+                    _ => return Err(format!("unexpected argument: {}", arg)),
+                }
+            }
+
+            if all {
+                if !days.is_empty() || input.is_some() {
+                    return Err("--all can't be combined with a day or --input".to_string());
+                }
+                days = all_days();
+            }
+            if days.is_empty() {
+                return Err("expected a day number or --all".to_string());
+            }
+
+            Ok(Command::Run { days, part, input, format, jobs })
+        },
+        Some("bench") => {
+            let mut days = Vec::new();
+            let mut all = false;
+            let mut iterations = 10;
+            let mut save = None;
+            let mut baseline = None;
+            let mut threshold = 10;
+
+            while let Some(arg) = it.next() {
+                match arg.as_str() {
+                    "--all" => all = true,
+                    "--iterations" => {
+                        iterations = parse_number(it.next(), arg)?;
+                        if iterations == 0 {
+                            return Err("--iterations must be positive".to_string());
+                        }
+                    },
+                    "--save" => save = Some(it.next().ok_or("--save expects a path")?.to_owned()),
+                    "--baseline" => baseline = Some(it.next().ok_or("--baseline expects a path")?.to_owned()),
+                    "--threshold" => threshold = parse_number(it.next(), arg)?,
+                    day if days.is_empty() && !day.starts_with("--") => days.push(parse_day(day)?),
+                    _ => return Err(format!("unexpected argument: {}", arg)),
+                }
+            }
+
+            if all {
+                if !days.is_empty() {
+                    return Err("--all can't be combined with a day".to_string());
+                }
+                days = all_days();
+            }
+            if days.is_empty() {
+                return Err("expected a day number or --all".to_string());
+            }
+
+            Ok(Command::Bench { days, iterations, save, baseline, threshold })
+        },
+        Some("verify") => {
+            let mut answers = ANSWERS.to_string();
+            while let Some(arg) = it.next() {
+                match arg.as_str() {
+                    "--answers" => {
+                        let path = it.next().ok_or("--answers expects a path")?;
+                        answers = path.to_owned();
+                    },
+                    _ => return Err(format!("unexpected argument: {}", arg)),
+                }
+            }
+            Ok(Command::Verify { answers })
+        },
+        Some("survey") => {
+            let mut query = None;
+            let mut input = None;
+            while let Some(arg) = it.next() {
+                match arg.as_str() {
+                    "--input" => {
+                        let path = it.next().ok_or("--input expects a path")?;
+                        input = Some(path.to_owned());
+                    },
+                    q if query.is_none() && !q.starts_with("--") => query = Some(q.to_owned()),
+                    _ => return Err(format!("unexpected argument: {}", arg)),
+                }
+            }
+            let query = query.ok_or("expected a query")?;
+            Ok(Command::Survey { query, input })
+        },
+        Some("debug") => {
+            let mut script = None;
+            let mut input = None;
+            while let Some(arg) = it.next() {
+                match arg.as_str() {
+                    "--input" => {
+                        let path = it.next().ok_or("--input expects a path")?;
+                        input = Some(path.to_owned());
+                    },
+                    s if script.is_none() && !s.starts_with("--") => script = Some(s.to_owned()),
+                    _ => return Err(format!("unexpected argument: {}", arg)),
+                }
+            }
+            let script = script.ok_or("expected a script")?;
+            Ok(Command::Debug { script, input })
+        },
+        Some(cmd) => Err(format!("unknown command: {}", cmd)),
+        None => Err("missing command".to_string()),
+    }
+}
+
+fn default_input(day: usize) -> String {
+    format!("inputs/day{:02}.txt", day)
+}
+
+// Read the input normalized to `\n` line endings, from stdin if the path is `-`.
+fn read_input(path: &str) -> Result<String, String> {
+    let input = if path == "-" {
+        Input::from_reader(io::stdin().lock())
+            .map_err(|e| format!("failed to read stdin: {}", e))?
+    } else {
+        Input::from_path(path)
+            .map_err(|e| format!("failed to read {}: {}", path, e))?
+    };
+    Ok(input.text())
+}
+
+// Input text and parts to solve of the day.
+fn job(number: usize, part: Option<usize>, path: &str) -> Result<Job, String> {
+    let day = advent_of_code_2020::day(number).unwrap();
+    let parts = match part {
+        Some(p) if p > day.parts() => {
+            return Err(format!("day {:02} has no part {}", day.number(), p));
+        },
+        Some(p) => vec![p],
+        None => (1..=day.parts()).collect(),
+    };
+    let text = read_input(path)?;
+    Ok(Job { day, text, parts })
+}
+
+// Solve the days one by one, or on a thread pool if `threads` is set, printing results in day order.
+// Returns true if every day was solved.
+fn run(days: &[usize], part: Option<usize>, input: Option<&str>, format: Format, threads: Option<usize>) -> bool {
+    let start = Instant::now();
+    let mut cpu = Duration::from_nanos(0);
+    let mut answers = Vec::new();
+    let mut failed = 0;
+
+    let mut report = |result: Result<(Duration, Vec<Answer>), String>| match result {
+        Ok((elapsed, day_answers)) => {
+            cpu += elapsed + day_answers.iter().map(|a| a.elapsed).sum::<Duration>();
+            if format == Format::Text {
+                if let Some(a) = day_answers.first() {
+                    println!("day {:02} parse ({:?})", a.day, elapsed);
+                }
+                for a in &day_answers {
+                    println!("day {:02} part {}: {} ({:?})", a.day, a.part, a.answer, a.elapsed);
+                }
+            }
+            answers.extend(day_answers);
+        },
+        Err(e) => {
+            eprintln!("error: {}", e);
+            failed += 1;
+        }
+    };
+
+    let jobs = days.iter()
+        .map(|n| job(*n, part, &input.map(|p| p.to_string()).unwrap_or_else(|| default_input(*n))));
+    match threads {
+        None => {
+            for job in jobs {
+                report(job.and_then(|job| runner::run(&job.day, &job.text, &job.parts).map_err(|e| e.to_string())));
+            }
+        },
+        Some(threads) => {
+            // Days that failed to load are reported in their place among the solved ones.
+            let mut ready = Vec::new();
+            let mut errors = Vec::new();
+            for job in jobs {
+                match job {
+                    Ok(job) => {
+                        ready.push(job);
+                        errors.push(None);
+                    },
+                    Err(e) => errors.push(Some(e)),
+                }
+            }
+            let mut results = runner::run_all(&ready, threads).into_iter();
+            for error in errors {
+                report(match error {
+                    Some(e) => Err(e),
+                    None => results.next().unwrap().map_err(|e| e.to_string()),
+                });
+            }
+        },
+    }
+
+    if format == Format::Json {
+        print!("{}", runner::to_json(&answers));
+    }
+    if threads.is_some() && format == Format::Text {
+        let wall = start.elapsed();
+        println!("wall {:?}, cpu {:?} ({:.1}x)", wall, cpu, cpu.as_secs_f64() / wall.as_secs_f64());
+    }
+    failed == 0
+}
+
+// Returns true if no part failed (missing inputs or answers are reported, but not failures).
+fn verify(path: &str) -> Result<bool, String> {
+    let text = read_input(path)?;
+    let answers = Answers::parse(&text)
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    let (mut passed, mut failed, mut missing) = (0, 0, 0);
+    for day in advent_of_code_2020::days() {
+        let text = read_input(&default_input(day.number())).ok();
+        for (p, outcome) in answers::check(&day, text.as_deref(), &answers) {
+            match outcome {
+                Outcome::Pass => {
+                    passed += 1;
+                    println!("day {:02} part {}: pass", day.number(), p);
+                },
+                Outcome::Fail(reason) => {
+                    failed += 1;
+                    println!("day {:02} part {}: FAIL ({})", day.number(), p, reason);
+                },
+                Outcome::Missing(reason) => {
+                    missing += 1;
+                    println!("day {:02} part {}: missing ({})", day.number(), p, reason);
+                },
+            }
+        }
+    }
+
+    println!("{} passed, {} failed, {} missing", passed, failed, missing);
+    Ok(failed == 0)
+}
+
+fn print_timing(t: &Timing) {
+    println!("day {:02} {}: min {:?}, median {:?}, max {:?}", t.day, t.stage, t.min, t.median, t.max);
+}
+
+// Returns true if every day was benchmarked and no regressions were found.
+fn bench(days: &[usize], iterations: usize, save: Option<&str>, baseline: Option<&str>, threshold: u32) -> Result<bool, String> {
+    let baseline = match baseline {
+        Some(path) => {
+            let text = read_input(path)?;
+            Some(bench::from_json(&text).map_err(|e| format!("{}: {}", path, e))?)
+        },
+        None => None,
+    };
+
+    let mut ok = true;
+    let mut timings = Vec::new();
+    for number in days {
+        let day = advent_of_code_2020::day(*number).unwrap();
+        let result = read_input(&default_input(*number))
+            .and_then(|text| bench::bench(&day, &text, iterations).map_err(|e| e.to_string()));
+        match result {
+            Ok(day_timings) => {
+                day_timings.iter().for_each(print_timing);
+                timings.extend(day_timings);
+            },
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ok = false;
+            }
+        }
+    }
+
+    if let Some(path) = save {
+        fs::write(path, bench::to_json(&timings))
+            .map_err(|e| format!("failed to write {}: {}", path, e))?;
+        println!("saved {} timing(s) to {}", timings.len(), path);
+    }
+
+    if let Some(baseline) = baseline {
+        let regressions = bench::compare(&timings, &baseline, threshold as f64);
+        for r in &regressions {
+            println!("day {:02} {}: REGRESSION median {:?} -> {:?} (+{:.1}%)",
+                     r.day, r.stage, r.baseline, r.current, r.percent());
+        }
+        println!("{} regression(s) above {}% threshold", regressions.len(), threshold);
+        ok = ok && regressions.is_empty();
+    }
+    Ok(ok)
+}
+
+fn survey(query: &str, path: &str) -> Result<(), String> {
+    let expr = Expr::parse(query).map_err(|e| e.to_string())?;
+    let text = read_input(path)?;
+    let groups = Day06::parse(&text).map_err(|e| e.on(6).to_string())?;
+    println!("{}", expr.count(&groups).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+fn debug(script: &str, path: &str) -> Result<(), String> {
+    let text = fs::read_to_string(script).map_err(|e| format!("failed to read {}: {}", script, e))?;
+    let commands = day08::script(&text).map_err(|e| format!("{}: {}", script, e))?;
+    let text = read_input(path)?;
+    let code = Day08::parse(&text).map_err(|e| e.on(8).to_string())?;
+    let mut debugger = Debugger::new(&code);
+    for command in &commands {
+        let output = debugger.exec(command);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+    }
+    Ok(())
+}
+
+fn list() {
+    for day in advent_of_code_2020::days() {
+        let path = default_input(day.number());
+        let missing = if fs::metadata(&path).is_ok() { "" } else { " (missing)" };
+        println!("day {:02}: {} part(s), input {}{}", day.number(), day.parts(), path, missing);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match command {
+        Command::List => list(),
+        Command::Run { days, part, input, format, jobs } => {
+            if !run(&days, part, input.as_deref(), format, jobs) {
+                process::exit(1);
+            }
+        },
+        Command::Survey { query, input } => {
+            let path = input.unwrap_or_else(|| default_input(6));
+            if let Err(e) = survey(&query, &path) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        },
+        Command::Debug { script, input } => {
+            let path = input.unwrap_or_else(|| default_input(8));
+            if let Err(e) = debug(&script, &path) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        },
+        Command::Verify { answers } => {
+            match verify(&answers) {
+                Ok(true) => (),
+                Ok(false) => process::exit(1),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    process::exit(1);
+                }
+            }
+        },
+        Command::Bench { days, iterations, save, baseline, threshold } => {
+            match bench(&days, iterations, save.as_deref(), baseline.as_deref(), threshold) {
+                Ok(true) => (),
+                Ok(false) => process::exit(1),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&args("list")), Ok(Command::List));
+        assert_eq!(parse_args(&args("run 7 --part 2 --input x.txt")), Ok(Command::Run {
+            days: vec![7],
+            part: Some(2),
+            input: Some("x.txt".to_string()),
+            format: Format::Text,
+            jobs: None,
+        }));
+        assert_eq!(parse_args(&args("run --all")), Ok(Command::Run {
+            days: (1..=25).collect(),
+            part: None,
+            input: None,
+            format: Format::Text,
+            jobs: None,
+        }));
+        assert_eq!(parse_args(&args("run 21 --format json")), Ok(Command::Run {
+            days: vec![21],
+            part: None,
+            input: None,
+            format: Format::Json,
+            jobs: None,
+        }));
+        assert_eq!(parse_args(&args("run --all --jobs 4")), Ok(Command::Run {
+            days: (1..=25).collect(),
+            part: None,
+            input: None,
+            format: Format::Text,
+            jobs: Some(4),
+        }));
+        assert_eq!(parse_args(&args("survey count(union) --input x.txt")), Ok(Command::Survey {
+            query: "count(union)".to_string(),
+            input: Some("x.txt".to_string()),
+        }));
+        assert_eq!(parse_args(&args("debug loop.txt")), Ok(Command::Debug {
+            script: "loop.txt".to_string(),
+            input: None,
+        }));
+        assert_eq!(parse_args(&args("debug loop.txt --input -")), Ok(Command::Debug {
+            script: "loop.txt".to_string(),
+            input: Some("-".to_string()),
+        }));
+        assert_eq!(parse_args(&args("verify")), Ok(Command::Verify {
+            answers: ANSWERS.to_string(),
+        }));
+        assert_eq!(parse_args(&args("verify --answers a.txt")), Ok(Command::Verify {
+            answers: "a.txt".to_string(),
+        }));
+        assert_eq!(parse_args(&args("bench 15")), Ok(Command::Bench {
+            days: vec![15],
+            iterations: 10,
+            save: None,
+            baseline: None,
+            threshold: 10,
+        }));
+        assert_eq!(parse_args(&args("bench --all --iterations 3 --save b.json --baseline a.json --threshold 25")),
+            Ok(Command::Bench {
+                days: (1..=25).collect(),
+                iterations: 3,
+                save: Some("b.json".to_string()),
+                baseline: Some("a.json".to_string()),
+                threshold: 25,
+            }));
+    }
+
+    #[test]
+    fn test_parse_args_invalid() {
+        let cases = vec![
+            "",
+            "walk",
+            "list 1",
+            "run",
+            "run 26",
+            "run 1 2",
+            "run 1 --part 3",
+            "run 1 --input",
+            "run --all 5",
+            "run --all --input x.txt",
+            "run 1 --format",
+            "run 1 --format xml",
+            "run --all --jobs",
+            "run --all --jobs 0",
+            "verify 1",
+            "survey",
+            "survey union all",
+            "survey union --input",
+            "debug",
+            "debug a.txt b.txt",
+            "debug a.txt --input",
+            "verify --answers",
+            "bench",
+            "bench 1 --iterations 0",
+            "bench 1 --iterations x",
+            "bench 1 --threshold",
+            "bench --all 1",
+        ];
+
+        for line in cases {
+            assert!(parse_args(&args(line)).is_err(), "{}", line);
+        }
+    }
+}
@@ -1,78 +1,199 @@
-use crate::utils::lines;
+use crate::error::{number, parse_lines, Result};
+use crate::solution::Solution;
 
-pub fn input() -> Vec<i64> {
-    lines().into_iter()
-        .map(|line| line.parse().unwrap())
-        .collect()
+pub fn input(text: &str) -> Result<Vec<i64>> {
+    parse_lines(text.lines(), number)
 }
 
-/// Find pair of numbers from input slice that add up to provided sum value.
-pub fn find2(sorted: &[i64], sum: i64) -> Option<(i64, i64)> {
-    let mut lo: usize = 0;
-    let mut hi: usize = sorted.len() - 1;
-    loop {
-        if lo >= hi {
-            break;
-        }
+const TARGET: i64 = 2020;
+
+// Pairs of indices from `lo..=hi` of the sorted slice with values adding up to `target`.
+fn pairs<F>(sorted: &[i64], mut lo: usize, mut hi: usize, target: i64, picked: &mut Vec<usize>, f: &mut F) -> bool
+    where
+        F: FnMut(&[usize]) -> bool
+{
+    let mut emit = |i: usize, j: usize, picked: &mut Vec<usize>| {
+        picked.push(i);
+        picked.push(j);
+        let stop = f(picked);
+        picked.truncate(picked.len() - 2);
+        stop
+    };
 
-        let a = sorted[lo];
-        let b = sorted[hi];
-        let added = a + b;
-        if added == sum {
-            return Some((a, b));
-        } else if added < sum {
+    while lo < hi {
+        let (a, b) = (sorted[lo], sorted[hi]);
+        if a + b < target {
             lo += 1;
-        } else {
+        } else if a + b > target {
             hi -= 1;
+        } else if a == b {
+            // All the values in between are equal too, so any two of them fit.
+            for i in lo..hi {
+                for j in i + 1..=hi {
+                    if emit(i, j, picked) {
+                        return true;
+                    }
+                }
+            }
+            return false;
+        } else {
+            let lo_end = lo + sorted[lo..hi].iter().take_while(|x| **x == a).count();
+            let hi_start = hi - sorted[lo_end..hi].iter().rev().take_while(|x| **x == b).count();
+            for i in lo..lo_end {
+                for j in hi_start..=hi {
+                    if emit(i, j, picked) {
+                        return true;
+                    }
+                }
+            }
+            lo = lo_end;
+            hi = hi_start - 1;
         }
     }
-
-    None
-}
-
-fn solve2(input: &Vec<i64>) -> i64 {
-    let (a, b) = find2(input, 2020).unwrap();
-    a * b
+    false
 }
 
-/// Find three numbers from input slice that add up to provided sum value.
-fn find3(input: &[i64], sum: i64) -> Option<(i64, i64, i64)> {
-    let n = input.len();
-    for i in 0..n {
-        let a = input[i];
-        let remaining = exclude(input, i);
+// Extend `picked` with indices from `from..` of `k` more values adding up to `target`.
+fn search<F>(sorted: &[i64], from: usize, target: i64, k: usize, picked: &mut Vec<usize>, f: &mut F) -> bool
+    where
+        F: FnMut(&[usize]) -> bool
+{
+    let n = sorted.len();
+    if n - from < k {
+        return false;
+    }
+    match k {
+        0 => target == 0 && f(picked),
+        1 => {
+            let lo = from + sorted[from..].partition_point(|x| *x < target);
+            let hi = from + sorted[from..].partition_point(|x| *x <= target);
+            for i in lo..hi {
+                picked.push(i);
+                let stop = f(picked);
+                picked.pop();
+                if stop {
+                    return true;
+                }
+            }
+            false
+        },
+        2 => pairs(sorted, from, n - 1, target, picked, f),
+        _ => {
+            for i in from..=n - k {
+                // Skip what can't add up: smallest and largest possible sums starting with sorted[i].
+                let min: i64 = sorted[i..i + k].iter().sum();
+                if min > target {
+                    break;
+                }
+                let max: i64 = sorted[i] + sorted[n - k + 1..].iter().sum::<i64>();
+                if max < target {
+                    continue;
+                }
 
-        if let Some((b, c)) = find2(&remaining, sum - a) {
-            return Some((a, b, c));
+                picked.push(i);
+                let stop = search(sorted, i + 1, target - sorted[i], k - 1, picked, f);
+                picked.pop();
+                if stop {
+                    return true;
+                }
+            }
+            false
         }
     }
+}
 
-    None
+/// Call `f` with indices (in increasing order) of every `k` values of the sorted slice adding up
+/// to `target`, until `f` returns true. Indices come in a buffer reused between calls.
+pub fn ksum_with<F: FnMut(&[usize]) -> bool>(sorted: &[i64], target: i64, k: usize, mut f: F) {
+    let mut picked = Vec::with_capacity(k);
+    search(sorted, 0, target, k, &mut picked, &mut f);
 }
 
-/// Return new Vector with element at given index missing (excluded)
-fn exclude<T: Clone + 'static>(slice: &[T], index: usize) -> Vec<T> {
-    slice.iter().enumerate()
-        .filter(|(i, _)| *i != index)
-        .map(|(_, e)| e.clone())
-        .collect()
+/// All distinct tuples of `k` indices of the sorted slice with values adding up to `target`.
+pub fn ksum(sorted: &[i64], target: i64, k: usize) -> Vec<Vec<usize>> {
+    let mut found = Vec::new();
+    ksum_with(sorted, target, k, |indices| {
+        found.push(indices.to_vec());
+        false
+    });
+    found
 }
 
-fn solve3(input: &Vec<i64>) -> i64 {
-    let (a, b, c) = find3(input, 2020).unwrap();
-    a * b * c
+/// First tuple of `k` indices of the sorted slice with values adding up to `target`.
+pub fn ksum_first(sorted: &[i64], target: i64, k: usize) -> Option<Vec<usize>> {
+    let mut found = None;
+    ksum_with(sorted, target, k, |indices| {
+        found = Some(indices.to_vec());
+        true
+    });
+    found
 }
 
-pub fn main() {
-    let input = {
-        let mut xs = input();
+/// Product of the first `k` values of the sorted slice adding up to `target`.
+pub fn solve(sorted: &[i64], target: i64, k: usize) -> Option<i64> {
+    ksum_first(sorted, target, k)
+        .map(|indices| indices.iter().map(|i| sorted[*i]).product())
+}
+
+pub struct Day01;
+
+impl Solution for Day01 {
+    type Input = Vec<i64>;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        let mut xs = input(text)?;
         xs.sort();
-        xs
-    };
+        Ok(xs)
+    }
+
+    fn part1(input: &Self::Input) -> i64 {
+        solve(input, TARGET, 2).unwrap()
+    }
+
+    fn part2(input: &Self::Input) -> i64 {
+        solve(input, TARGET, 3).unwrap()
+    }
+}
 
-    let answer = solve2(&input);
-    println!("{}", answer);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let answer = solve3(&input);
-    println!("{}", answer);
+    #[test]
+    fn test_example() {
+        let mut input = vec![1721, 979, 366, 299, 675, 1456];
+        input.sort();
+
+        assert_eq!(solve(&input, 2020, 2), Some(514579));
+        assert_eq!(solve(&input, 2020, 3), Some(241861950));
+        assert_eq!(solve(&input, 2021, 2), None);
+        assert_eq!(solve(&input, 1721 + 299, 2), Some(1721 * 299));
+    }
+
+    #[test]
+    fn test_ksum() {
+        let sorted = vec![-2, 1, 1, 1, 3, 4];
+
+        assert_eq!(ksum(&sorted, 2, 2), vec![vec![0, 5], vec![1, 2], vec![1, 3], vec![2, 3]]);
+        assert_eq!(ksum(&sorted, 5, 2), vec![vec![1, 5], vec![2, 5], vec![3, 5]]);
+        assert_eq!(ksum(&sorted, 3, 3), vec![vec![0, 1, 5], vec![0, 2, 5], vec![0, 3, 5], vec![1, 2, 3]]);
+        assert_eq!(ksum(&sorted, 1, 1), vec![vec![1], vec![2], vec![3]]);
+        assert_eq!(ksum(&sorted, 9, 4), vec![vec![1, 2, 4, 5], vec![1, 3, 4, 5], vec![2, 3, 4, 5]]);
+        assert_eq!(ksum(&sorted, 0, 0), vec![Vec::<usize>::new()]);
+        assert_eq!(ksum(&sorted, 100, 7), Vec::<Vec<usize>>::new());
+        assert_eq!(ksum(&[], 0, 2), Vec::<Vec<usize>>::new());
+
+        assert_eq!(ksum_first(&sorted, 2, 2), Some(vec![0, 5]));
+        assert_eq!(ksum_first(&sorted, 8, 3), Some(vec![1, 4, 5]));
+        assert_eq!(ksum_first(&sorted, 50, 3), None);
+
+        let mut calls = 0;
+        ksum_with(&sorted, 2, 2, |_| {
+            calls += 1;
+            calls == 2
+        });
+        assert_eq!(calls, 2);
+    }
 }
@@ -1,55 +1,200 @@
+#![allow(clippy::useless_conversion)]
+
 use regex::Regex;
+use std::collections::HashSet;
 
-use crate::utils::lines;
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
 
+/// Password with the policy numbers and letter it was written down with: `lo-hi chr: pwd`.
 #[derive(Eq, PartialEq, Debug)]
-pub(crate) struct Record {
-    pub(crate) lo: usize,
-    pub(crate) hi: usize,
-    pub(crate) chr: char,
-    pub(crate) pwd: String,
+pub struct Record {
+    pub lo: usize,
+    pub hi: usize,
+    pub chr: char,
+    pub pwd: String,
 }
 
-impl Record {
-    fn is_valid(&self) -> bool {
-        let count = self.pwd.chars().filter(|c| *c == self.chr).count();
-        (count >= self.lo) && (count <= self.hi)
+pub fn parse(line: &str) -> Result<Record> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(\d+)-(\d+)\s(\w): (\w+)$").unwrap();
     }
+    let cap = RE.captures(line)
+        .ok_or_else(|| Error::new(line, "expected `lo-hi c: password`"))?;
+    let lo: usize = number(&cap[1])?;
+    let hi: usize = number(&cap[2])?;
+    let chr = cap[3].chars().next().unwrap();
+    let pwd = cap[4].to_string();
+    Ok(Record { lo, hi, chr, pwd })
 }
 
-pub(crate) fn parse(line: String) -> Result<Record, ()> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"(\d+)-(\d+)\s(\w): (\w+)").unwrap();
+pub fn input(text: &str) -> Result<Vec<Record>> {
+    parse_lines(text.lines(), parse)
+}
+
+/// Rule the password of a record must satisfy.
+pub trait PasswordPolicy {
+    /// Human-readable clause of the policy as applied to the record, e.g. `1-3 of 'a'`.
+    fn describe(&self, record: &Record) -> String;
+
+    fn is_valid(&self, record: &Record) -> bool;
+
+    /// Clauses the record fails, empty if the password is valid.
+    fn failures(&self, record: &Record) -> Vec<String> {
+        if self.is_valid(record) {
+            vec![]
+        } else {
+            vec![self.describe(record)]
+        }
     }
-    let cap = RE.captures(&line).unwrap();
-    let lo: usize = cap.get(1).unwrap().as_str().parse().unwrap();
-    let hi: usize = cap.get(2).unwrap().as_str().parse().unwrap();
-    let chr = cap.get(3).unwrap().as_str().chars().next().unwrap();
-    let pwd = cap.get(4).unwrap().as_str().to_string();
-    Ok(Record { lo, hi, chr, pwd })
 }
 
-pub(crate) fn input() -> Vec<Record> {
-    lines().into_iter()
-        .map(|line| parse(line).unwrap())
+/// The letter occurs between `lo` and `hi` times (part 1).
+pub struct CountRange;
+
+impl PasswordPolicy for CountRange {
+    fn describe(&self, record: &Record) -> String {
+        format!("{}-{} of {:?}", record.lo, record.hi, record.chr)
+    }
+
+    fn is_valid(&self, record: &Record) -> bool {
+        let count = record.pwd.chars().filter(|c| *c == record.chr).count();
+        (count >= record.lo) && (count <= record.hi)
+    }
+}
+
+/// The letter is at exactly one of 1-based positions `lo` and `hi` (part 2).
+pub struct Positions;
+
+impl PasswordPolicy for Positions {
+    fn describe(&self, record: &Record) -> String {
+        format!("{:?} at exactly one of positions {} and {}", record.chr, record.lo, record.hi)
+    }
+
+    fn is_valid(&self, record: &Record) -> bool {
+        record.pwd.chars().enumerate().into_iter()
+            .map(|(i, c)| (i+1, c))
+            .filter(|(i, _)| (*i == record.lo) || (*i == record.hi))
+            .filter(|(_, c)| *c == record.chr)
+            .count() == 1
+    }
+}
+
+/// The password matches the regular expression.
+pub struct Matches(Regex);
+
+impl Matches {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Regex::new(pattern)
+            .map(Matches)
+            .map_err(|_| Error::new(pattern, "invalid regex"))
+    }
+}
+
+impl PasswordPolicy for Matches {
+    fn describe(&self, _: &Record) -> String {
+        format!("matches /{}/", self.0.as_str())
+    }
+
+    fn is_valid(&self, record: &Record) -> bool {
+        self.0.is_match(&record.pwd)
+    }
+}
+
+/// The password has at least that many distinct characters.
+pub struct DistinctChars(pub usize);
+
+impl PasswordPolicy for DistinctChars {
+    fn describe(&self, _: &Record) -> String {
+        format!("at least {} distinct characters", self.0)
+    }
+
+    fn is_valid(&self, record: &Record) -> bool {
+        record.pwd.chars().collect::<HashSet<_>>().len() >= self.0
+    }
+}
+
+fn describe_all(policies: &[Box<dyn PasswordPolicy>], record: &Record, op: &str) -> String {
+    let clauses = policies.iter()
+        .map(|p| p.describe(record))
+        .collect::<Vec<_>>();
+    format!("({})", clauses.join(op))
+}
+
+/// Every policy holds, failures of each of them are reported.
+pub struct And(pub Vec<Box<dyn PasswordPolicy>>);
+
+impl PasswordPolicy for And {
+    fn describe(&self, record: &Record) -> String {
+        describe_all(&self.0, record, " and ")
+    }
+
+    fn is_valid(&self, record: &Record) -> bool {
+        self.0.iter().all(|p| p.is_valid(record))
+    }
+
+    fn failures(&self, record: &Record) -> Vec<String> {
+        self.0.iter()
+            .flat_map(|p| p.failures(record))
+            .collect()
+    }
+}
+
+/// At least one policy holds.
+pub struct Or(pub Vec<Box<dyn PasswordPolicy>>);
+
+impl PasswordPolicy for Or {
+    fn describe(&self, record: &Record) -> String {
+        describe_all(&self.0, record, " or ")
+    }
+
+    fn is_valid(&self, record: &Record) -> bool {
+        self.0.iter().any(|p| p.is_valid(record))
+    }
+}
+
+/// The policy does not hold.
+pub struct Not(pub Box<dyn PasswordPolicy>);
+
+impl PasswordPolicy for Not {
+    fn describe(&self, record: &Record) -> String {
+        format!("not {}", self.0.describe(record))
+    }
+
+    fn is_valid(&self, record: &Record) -> bool {
+        !self.0.is_valid(record)
+    }
+}
+
+/// Failed clauses of the policy for every record, in the order of records.
+pub fn audit<'a>(records: &'a [Record], policy: &dyn PasswordPolicy) -> Vec<(&'a Record, Vec<String>)> {
+    records.iter()
+        .map(|record| (record, policy.failures(record)))
         .collect()
 }
 
-fn is_valid(record: &Record) -> bool {
-    record.pwd.chars().enumerate().into_iter()
-        .map(|(i, c)| (i+1, c))
-        .filter(|(i, _)| (*i == record.lo) || (*i == record.hi))
-        .filter(|(_, c)| *c == record.chr)
-        .count() == 1
+fn count_valid(records: &[Record], policy: &dyn PasswordPolicy) -> usize {
+    records.iter().filter(|r| policy.is_valid(r)).count()
 }
 
-pub fn main() {
-    let input = input();
-    let valid = input.iter().filter(|r| r.is_valid()).count();
-    println!("{}", valid);
+pub struct Day02;
+
+impl Solution for Day02 {
+    type Input = Vec<Record>;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
+
+    fn part1(input: &Self::Input) -> usize {
+        count_valid(input, &CountRange)
+    }
 
-    let valid = input.iter().filter(|r| is_valid(r)).count();
-    println!("{}", valid);
+    fn part2(input: &Self::Input) -> usize {
+        count_valid(input, &Positions)
+    }
 }
 
 #[cfg(test)]
@@ -58,11 +203,21 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        let line = "10-12 l: kllswlmlglps".to_string();
+        let line = "10-12 l: kllswlmlglps";
         let expected = Record { lo: 10, hi: 12, chr: 'l', pwd: "kllswlmlglps".to_string() };
         assert_eq!(parse(line).unwrap(), expected);
     }
 
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("10-12 l kllswlmlglps").is_err());
+        assert!(parse("10-99999999999999999999 l: kllswlmlglps").is_err());
+
+        let e = input("1-3 a: abcde\n1-3 b cdefg").unwrap_err();
+        assert_eq!(e.line(), Some(2));
+        assert_eq!(e.text(), "1-3 b cdefg");
+    }
+
     // 1-3 a: abcde is valid: position 1 contains a and position 3 does not.
     // 1-3 b: cdefg is invalid: neither position 1 nor position 3 contains b.
     // 2-9 c: ccccccccc is invalid: both position 2 and position 9 contain c.
@@ -76,9 +231,44 @@ mod tests {
 
         for (line, valid) in cases {
             println!("{}", line);
-            let rec = parse(line.to_string()).unwrap();
-            assert_eq!(is_valid(&rec), valid);
+            let rec = parse(line).unwrap();
+            assert_eq!(Positions.is_valid(&rec), valid);
         }
     }
 
+    #[test]
+    fn test_policies() {
+        let rec = parse("1-3 a: abcde").unwrap();
+
+        assert!(CountRange.is_valid(&rec));
+        assert!(Matches::new("^[a-e]+$").unwrap().is_valid(&rec));
+        assert!(!Matches::new("z").unwrap().is_valid(&rec));
+        assert!(Matches::new("(").is_err());
+        assert!(DistinctChars(5).is_valid(&rec));
+        assert!(!DistinctChars(6).is_valid(&rec));
+
+        assert!(And(vec![Box::new(CountRange), Box::new(Positions)]).is_valid(&rec));
+        assert!(Or(vec![Box::new(DistinctChars(6)), Box::new(Positions)]).is_valid(&rec));
+        assert!(!Or(vec![Box::new(DistinctChars(6)), Box::new(Not(Box::new(Positions)))]).is_valid(&rec));
+        assert!(Not(Box::new(DistinctChars(6))).is_valid(&rec));
+    }
+
+    #[test]
+    fn test_audit() {
+        let records = input("1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc").unwrap();
+        let policy = And(vec![
+            Box::new(Or(vec![Box::new(CountRange), Box::new(Positions)])),
+            Box::new(DistinctChars(2)),
+            Box::new(Not(Box::new(Matches::new("ccc").unwrap()))),
+        ]);
+
+        let failures = audit(&records, &policy).into_iter()
+            .map(|(_, failed)| failed)
+            .collect::<Vec<_>>();
+        assert_eq!(failures, vec![
+            vec![],
+            vec!["(1-3 of 'b' or 'b' at exactly one of positions 1 and 3)".to_string()],
+            vec!["at least 2 distinct characters".to_string(), "not matches /ccc/".to_string()],
+        ]);
+    }
 }
\ No newline at end of file
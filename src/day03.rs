@@ -1,9 +1,11 @@
-use crate::utils::lines;
+#![allow(clippy::single_match, clippy::unnecessary_fold, clippy::useless_vec)]
 
-pub fn input() -> Vec<Vec<char>> {
-    lines().into_iter()
-        .map(|line| line.chars().collect())
-        .collect()
+use crate::error::Result;
+use crate::grid::Grid;
+use crate::solution::Solution;
+
+pub fn input(text: &str) -> Result<Grid<char>> {
+    Grid::parse(text.lines(), |c| Some(c).filter(|c| *c == '.' || *c == '#'))
 }
 
 enum Cell {
@@ -31,25 +33,15 @@ impl Point {
     }
 }
 
-fn find(field: &Vec<Vec<char>>, point: &Point) -> Cell {
-    let (x, y) = {
-        let Point(mut x, y) = point;
-        let _rows = field.len();
-        let cols = field[0].len();
-        if x >= cols {
-            x = x % cols;
-        }
-        (x, *y)
-    };
-
-    let chr = field[y][x];
-    Cell::check(chr)
+fn find(field: &Grid<char>, point: &Point) -> Cell {
+    let Point(x, y) = *point;
+    Cell::check(*field.wrapped((y, x)))
 }
 
-pub fn count_trees(field: &Vec<Vec<char>>, mut point: Point, (dx, dy): (usize, usize)) -> usize {
+pub fn count_trees(field: &Grid<char>, mut point: Point, (dx, dy): (usize, usize)) -> usize {
     let mut trees: usize = 0;
-    while point.1 < field.len() {
-        match find(&field, &point) {
+    while point.1 < field.rows() {
+        match find(field, &point) {
             Cell::Tree => {
                 trees += 1;
             },
@@ -60,22 +52,32 @@ pub fn count_trees(field: &Vec<Vec<char>>, mut point: Point, (dx, dy): (usize, u
     trees
 }
 
-pub fn main() {
-    let field: Vec<Vec<char>> = input();
-    let trees = count_trees(&field, Point(0,0), (3, 1));
-    println!("{}", trees);
+pub struct Day03;
+
+impl Solution for Day03 {
+    type Input = Grid<char>;
+    type Part1 = usize;
+    type Part2 = usize;
 
-    let slopes = vec![
-        (1, 1),
-        (3, 1),
-        (5, 1),
-        (7, 1),
-        (1, 2),
-    ];
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
+
+    fn part1(field: &Self::Input) -> usize {
+        count_trees(field, Point(0,0), (3, 1))
+    }
 
-    let trees = slopes.iter()
-        .map(|d| count_trees(&field, Point(0,0), *d))
-        .fold(1, |acc, t| acc * t);
-    println!("{}", trees);
+    fn part2(field: &Self::Input) -> usize {
+        let slopes = vec![
+            (1, 1),
+            (3, 1),
+            (5, 1),
+            (7, 1),
+            (1, 2),
+        ];
 
+        slopes.iter()
+            .map(|d| count_trees(field, Point(0,0), *d))
+            .fold(1, |acc, t| acc * t)
+    }
 }
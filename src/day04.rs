@@ -9,7 +9,10 @@
 // cid (Country ID)
 
 use std::collections::HashMap;
-use crate::utils::lines;
+use regex::Regex;
+use crate::error::{number, parse_lines, Error, Result};
+use crate::input::Input;
+use crate::solution::Solution;
 
 pub const BYR: &str = "byr";
 pub const IYR: &str = "iyr";
@@ -20,124 +23,272 @@ pub const ECL: &str = "ecl";
 pub const PID: &str = "pid";
 pub const CID: &str = "cid";
 
+/// Known fields in the canonical order.
+pub const FIELDS: [&str; 8] = [BYR, IYR, EYR, HGT, HCL, ECL, PID, CID];
+
 pub type Passport = HashMap<String, String>;
 
-pub fn input() -> Vec<Passport> {
-    lines().split(|line| line.is_empty())
-        .map(|entry| parse(entry.join(" ")))
-        .collect()
+pub fn input(text: &str) -> Result<Vec<Passport>> {
+    Input::from(text).parse_paragraphs(|entry| {
+        parse_lines(entry.iter().map(|line| line.as_str()), parse)
+            .map(|parts| parts.into_iter().flatten().collect())
+    })
 }
 
-pub fn parse(line: String) -> Passport {
-    line.split(" ")
+pub fn parse(line: &str) -> Result<Passport> {
+    line.split_whitespace()
         .map(|kv| {
-            let tokens: Vec<&str> = kv.split(":").collect();
-            (tokens[0].to_owned(), tokens[1].to_owned())
+            let tokens: Vec<&str> = kv.split(':').collect();
+            if tokens.len() != 2 || tokens[0].is_empty() {
+                return Err(Error::new(kv, "expected `key:value`"));
+            }
+            Ok((tokens[0].to_owned(), tokens[1].to_owned()))
         })
-        .fold(Passport::new(), |mut p, (k ,v)| {
-            p.insert(k, v);
-            p
+        .collect()
+}
+
+/// Passport as a single line of `key:value` pairs: known fields in canonical order, then others by key.
+pub fn to_line(passport: &Passport) -> String {
+    let mut keys: Vec<&String> = passport.keys().collect();
+    keys.sort_by_key(|key| (FIELDS.iter().position(|f| f == key).unwrap_or(FIELDS.len()), key.to_owned()));
+    keys.into_iter()
+        .map(|key| format!("{}:{}", key, passport[key]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Passports in the batch format, one per line and separated by blank lines.
+/// Empty passports are skipped, as they can't be written.
+pub fn to_batch(passports: &[Passport]) -> String {
+    passports.iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| format!("{}\n", to_line(p)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Passport with unknown keys dropped and the hair colour lowercased, if it is a hex colour.
+pub fn normalize(passport: &Passport) -> Passport {
+    passport.iter()
+        .filter(|(key, _)| FIELDS.contains(&key.as_str()))
+        .map(|(key, value)| {
+            let is_hex = value.strip_prefix('#')
+                .map(|hex| !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                .unwrap_or_default();
+            let value = if key == HCL && is_hex { value.to_ascii_lowercase() } else { value.to_owned() };
+            (key.to_owned(), value)
         })
+        .collect()
 }
 
+/// All required fields are present (part 1), whether valid or not.
 pub fn is_valid(passport: &Passport) -> bool {
-    passport.contains_key(BYR) &&
-        passport.contains_key(IYR) &&
-        passport.contains_key(EYR) &&
-        passport.contains_key(HGT) &&
-        passport.contains_key(HCL) &&
-        passport.contains_key(ECL) &&
-        passport.contains_key(PID)
+    RULES.check(passport).iter()
+        .all(|(_, violation)| *violation != Violation::Missing)
 }
 
-fn is_valid_year(s: &str, lo: usize, hi: usize) -> bool {
-    let all_digits = s.len() == 4 && s.chars().all(|c| c.is_digit(10));
-    if !all_digits {
-        return false;
-    }
+/// Passport rules: `<key> = [optional] <rule>` per line, where rule is one of
+/// `any`, `year <lo>..<hi>`, `number [<unit>] <lo>..<hi>, ...`, `regex <pattern>` or `enum <value> ...`.
+pub const SCHEMA: &str = "\
+# Birth, issue and expiration years.
+byr = year 1920..2002
+iyr = year 2010..2020
+eyr = year 2020..2030
+# Height in centimeters or inches.
+hgt = number cm 150..193, in 59..76
+hcl = regex ^#[0-9a-f]{6}$
+ecl = enum amb blu brn gry grn hzl oth
+pid = regex ^[0-9]{9}$
+cid = optional any
+";
 
-    let year: usize = s.parse().unwrap();
-    if year < lo || year > hi {
-        return false;
-    }
+lazy_static! {
+    static ref RULES: Schema = Schema::parse(SCHEMA).unwrap();
+}
 
-    true
+#[derive(Debug, Clone)]
+pub enum Rule {
+    Any,
+    Year(u32, u32),
+    /// Number followed by a unit (possibly empty), with allowed range for each unit.
+    Number(Vec<(String, u32, u32)>),
+    Regex(Regex),
+    Enum(Vec<String>),
 }
 
-// byr (Birth Year) - four digits; at least 1920 and at most 2002.
-fn is_byr_valid(byr: &str) -> bool {
-    is_valid_year(byr, 1920, 2002)
+fn range(s: &str) -> Result<(u32, u32)> {
+    let (lo, hi) = s.trim().split_once("..")
+        .ok_or_else(|| Error::new(s, "expected `lo..hi`"))?;
+    let (lo, hi) = (number(lo)?, number(hi)?);
+    if lo > hi {
+        return Err(Error::new(s, "empty range"));
+    }
+    Ok((lo, hi))
 }
 
-// iyr (Issue Year) - four digits; at least 2010 and at most 2020.
-fn is_iyr_valid(iyr: &str) -> bool {
-    is_valid_year(iyr, 2010, 2020)
+impl Rule {
+    fn parse(kind: &str, args: &str) -> Result<Self> {
+        match kind {
+            "any" if args.is_empty() => Ok(Rule::Any),
+            "year" => range(args).map(|(lo, hi)| Rule::Year(lo, hi)),
+            "number" => args.split(',')
+                .map(|unit| {
+                    let unit = unit.trim();
+                    let (unit, r) = unit.split_once(' ').unwrap_or(("", unit));
+                    range(r).map(|(lo, hi)| (unit.to_string(), lo, hi))
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(Rule::Number),
+            "regex" => Regex::new(args)
+                .map(Rule::Regex)
+                .map_err(|_| Error::new(args, "invalid regex")),
+            "enum" if !args.is_empty() => Ok(Rule::Enum(args.split_whitespace().map(|s| s.to_string()).collect())),
+            _ => Err(Error::new(kind, "expected `any`, `year`, `number`, `regex` or `enum` with arguments")),
+        }
+    }
+
+    fn is_valid(&self, value: &str) -> bool {
+        match self {
+            Rule::Any => true,
+            Rule::Year(lo, hi) => value.len() == 4 && value.parse::<u32>()
+                .map(|year| (*lo..=*hi).contains(&year))
+                .unwrap_or_default(),
+            Rule::Number(units) => {
+                let (n, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len()));
+                match (n.parse::<u32>(), units.iter().find(|(u, _, _)| u == unit)) {
+                    (Ok(n), Some((_, lo, hi))) => (*lo..=*hi).contains(&n),
+                    _ => false,
+                }
+            },
+            Rule::Regex(re) => re.is_match(value),
+            Rule::Enum(values) => values.iter().any(|v| v == value),
+        }
+    }
+
+    fn expected(&self) -> String {
+        match self {
+            Rule::Any => "any value".to_string(),
+            Rule::Year(lo, hi) => format!("a year in {}..{}", lo, hi),
+            Rule::Number(units) => {
+                let units = units.iter()
+                    .map(|(unit, lo, hi)| format!("{}..{}{}", lo, hi, unit))
+                    .collect::<Vec<_>>();
+                units.join(" or ")
+            },
+            Rule::Regex(re) => format!("to match /{}/", re.as_str()),
+            Rule::Enum(values) => format!("one of {}", values.join(", ")),
+        }
+    }
+
+    /// Reason the value breaks the rule, if it does.
+    fn check(&self, value: &str) -> Option<String> {
+        if self.is_valid(value) {
+            None
+        } else {
+            Some(format!("expected {}", self.expected()))
+        }
+    }
 }
 
-// eyr (Expiration Year) - four digits; at least 2020 and at most 2030.
-fn is_eyr_valid(eyr: &str) -> bool {
-    is_valid_year(eyr, 2020, 2030)
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub key: String,
+    pub required: bool,
+    pub rule: Rule,
 }
 
-// hgt (Height) - a number followed by either cm or in:
-// If cm, the number must be at least 150 and at most 193.
-// If in, the number must be at least 59 and at most 76.
-fn is_hgt_valid(hgt: &str) -> bool {
-    let len = hgt.len();
-    let suffix: String = hgt.chars().skip(len - 2).collect();
-    let n: usize = hgt.chars().take(len - 2).collect::<String>().parse().unwrap();
-    match suffix.as_ref() {
-        "cm" => n >= 150 && n <= 193,
-        "in" => n >= 59 && n <= 76,
-        _ => false
+fn field(line: &str) -> Result<Option<Field>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (key, rule) = line.split_once('=')
+        .ok_or_else(|| Error::new(line, "expected `<key> = <rule>`"))?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return Err(Error::new(line, "invalid key"));
     }
-}
 
-// hcl (Hair Color) - a # followed by exactly six characters 0-9 or a-f.
-fn is_hcl_valid(hcl: &str) -> bool {
-    hcl.chars().next().unwrap() == '#' &&
-        hcl.to_lowercase().chars().skip(1).all(|c| c.is_digit(16))
+    let rule = rule.trim();
+    let (required, rule) = match rule.strip_prefix("optional ") {
+        Some(rule) => (false, rule.trim()),
+        None => (true, rule),
+    };
+    let (kind, args) = rule.split_once(' ').unwrap_or((rule, ""));
+    let rule = Rule::parse(kind, args.trim())
+        .map_err(|e| Error::new(line, e.reason()))?;
+    Ok(Some(Field { key: key.to_string(), required, rule }))
 }
 
-// ecl (Eye Color) - exactly one of: amb blu brn gry grn hzl oth.
-fn is_ecl_valid(ecl: &str) -> bool {
-    ecl == "amb" ||
-        ecl == "blu" ||
-        ecl == "brn" ||
-        ecl == "gry" ||
-        ecl == "grn" ||
-        ecl == "hzl" ||
-        ecl == "oth"
+/// Why a passport field breaks the schema.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Violation {
+    Missing,
+    Invalid(String),
 }
 
-// pid (Passport ID) - a nine-digit number, including leading zeroes.
-fn is_pid_valid(pid: &str) -> bool {
-    pid.len() == 9 && pid.chars().all(|c| c.is_digit(10))
+/// Rules for passport fields, in the order they are listed.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    fields: Vec<Field>,
 }
 
-// cid (Country ID) - ignored, missing or not.
+impl Schema {
+    pub fn parse(text: &str) -> Result<Self> {
+        let entries = parse_lines(text.lines(), field)?;
 
-fn is_really_valid(passport: &Passport) -> bool {
-    is_valid(&passport) &&
-        is_byr_valid(passport.get(BYR).unwrap()) &&
-        is_eyr_valid(passport.get(EYR).unwrap()) &&
-        is_iyr_valid(passport.get(IYR).unwrap()) &&
-        is_hgt_valid(passport.get(HGT).unwrap()) &&
-        is_hcl_valid(passport.get(HCL).unwrap()) &&
-        is_ecl_valid(passport.get(ECL).unwrap()) &&
-        is_pid_valid(passport.get(PID).unwrap())
+        let mut fields: Vec<Field> = Vec::new();
+        for (i, f) in entries.into_iter().enumerate() {
+            if let Some(f) = f {
+                if fields.iter().any(|g| g.key == f.key) {
+                    let line = text.lines().nth(i).unwrap_or_default();
+                    return Err(Error::new(line, "duplicate field").at(i + 1));
+                }
+                fields.push(f);
+            }
+        }
+        Ok(Self { fields })
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Violations of the passport, for each failed field in schema order. Unknown fields are ignored.
+    pub fn check(&self, passport: &Passport) -> Vec<(String, Violation)> {
+        self.fields.iter()
+            .filter_map(|f| {
+                let violation = match passport.get(&f.key) {
+                    None if f.required => Some(Violation::Missing),
+                    None => None,
+                    Some(value) => f.rule.check(value).map(Violation::Invalid),
+                };
+                violation.map(|v| (f.key.clone(), v))
+            })
+            .collect()
+    }
 }
 
-pub fn main() {
-    let passports = input();
-    let valid = passports.iter().filter(|p| is_valid(p)).count();
-    println!("{}", valid);
+pub struct Day04;
+
+impl Solution for Day04 {
+    type Input = Vec<Passport>;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
 
-    let really_valid = passports.iter()
-        .filter(|p| is_really_valid(p))
-        .count();
-    println!("{}", really_valid);
+    fn part1(passports: &Self::Input) -> usize {
+        passports.iter().filter(|p| is_valid(p)).count()
+    }
 
+    fn part2(passports: &Self::Input) -> usize {
+        passports.iter()
+            .filter(|p| RULES.check(p).is_empty())
+            .count()
+    }
 }
 
 #[cfg(test)]
@@ -146,8 +297,8 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        let line: String = "ecl:lzr cid:279 pid:192cm hcl:1f7352 iyr:2014 hgt:70cm eyr:1983 byr:2004".to_string();
-        let passport = parse(line);
+        let line = "ecl:lzr cid:279 pid:192cm hcl:1f7352 iyr:2014 hgt:70cm eyr:1983 byr:2004";
+        let passport = parse(line).unwrap();
 
         assert_eq!(passport.len(), 8);
         assert_eq!(passport.get(ECL).unwrap(), "lzr");
@@ -159,4 +310,95 @@ mod tests {
         assert_eq!(passport.get(EYR).unwrap(), "1983");
         assert_eq!(passport.get(BYR).unwrap(), "2004");
     }
+
+    #[test]
+    fn test_input() {
+        let text = "ecl:gry pid:860033327\nbyr:1937\n\nhcl:#cfa07d byr:1929";
+        let passports = input(text).unwrap();
+        assert_eq!(passports.len(), 2);
+        assert_eq!(passports[0].len(), 3);
+        assert_eq!(passports[1].get(BYR).unwrap(), "1929");
+
+        let e = input("ecl:gry\n\nhcl:#cfa07d\nbyr").unwrap_err();
+        assert_eq!(e.line(), Some(4));
+        assert_eq!(e.text(), "byr");
+    }
+
+    #[test]
+    fn test_hgt() {
+        let hgt = &RULES.fields()[3];
+        assert_eq!(hgt.key, HGT);
+
+        assert_eq!(hgt.rule.check("60in"), None);
+        assert_eq!(hgt.rule.check("190cm"), None);
+        assert_eq!(hgt.rule.check("190in"), Some("expected 150..193cm or 59..76in".to_string()));
+        assert!(hgt.rule.check("190").is_some());
+        assert!(hgt.rule.check("cm").is_some());
+        assert!(hgt.rule.check("5").is_some());
+        assert!(hgt.rule.check("").is_some());
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = Schema::parse("a = year 2000..2010\n\n# comment\nb = optional enum x y\nc = number 1..3\nd = regex ^z+$").unwrap();
+        assert_eq!(schema.fields().len(), 4);
+        assert!(!schema.fields()[1].required);
+
+        let passport = parse("a:1999 c:2 d:zz e:?").unwrap();
+        assert_eq!(schema.check(&passport), vec![
+            ("a".to_string(), Violation::Invalid("expected a year in 2000..2010".to_string())),
+        ]);
+
+        let passport = parse("b:z c:2x").unwrap();
+        assert_eq!(schema.check(&passport), vec![
+            ("a".to_string(), Violation::Missing),
+            ("b".to_string(), Violation::Invalid("expected one of x, y".to_string())),
+            ("c".to_string(), Violation::Invalid("expected 1..3".to_string())),
+            ("d".to_string(), Violation::Missing),
+        ]);
+    }
+
+    #[test]
+    fn test_schema_invalid() {
+        let e = Schema::parse("a = any\nb = year 2010..2000").unwrap_err();
+        assert_eq!((e.line(), e.reason()), (Some(2), "empty range"));
+
+        let e = Schema::parse("a = any\n\na = any").unwrap_err();
+        assert_eq!((e.line(), e.reason()), (Some(3), "duplicate field"));
+
+        assert!(Schema::parse("a any").is_err());
+        assert!(Schema::parse("a = colour red").is_err());
+        assert!(Schema::parse("a = regex (").is_err());
+        assert!(Schema::parse("a = enum").is_err());
+        assert!(Schema::parse("a = number cm 1..x").is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        let passports = input(include_str!("../inputs/examples/day04-invalid.txt")).unwrap();
+        let report = RULES.check(&passports[0]);
+        assert_eq!(report.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec![EYR, HGT, PID]);
+
+        let passports = input(include_str!("../inputs/examples/day04-valid.txt")).unwrap();
+        assert!(passports.iter().all(|p| RULES.check(p).is_empty()));
+    }
+
+    #[test]
+    fn test_to_batch() {
+        let passports = input(include_str!("../inputs/examples/day04-example.txt")).unwrap();
+        let batch = to_batch(&passports);
+        assert!(batch.starts_with("byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147\n\n"));
+        assert_eq!(input(&batch).unwrap(), passports);
+
+        let passport = parse("zz:1 pid:1 aa:2 byr:3").unwrap();
+        assert_eq!(to_line(&passport), "byr:3 pid:1 aa:2 zz:1");
+        assert_eq!(to_batch(&[Passport::new(), passport]), "byr:3 pid:1 aa:2 zz:1\n");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let text = "  hcl:#AbC123   foo:bar\n\tecl:BRN  \n\n\n\nfoo:baz\n\nhcl:Z12 byr:1937\n";
+        let passports = input(text).unwrap().iter().map(normalize).collect::<Vec<_>>();
+        assert_eq!(to_batch(&passports), "hcl:#abc123 ecl:BRN\n\nbyr:1937 hcl:Z12\n");
+    }
 }
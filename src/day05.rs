@@ -1,142 +1,153 @@
-use crate::utils::lines;
-
-#[derive(Debug, Eq, PartialEq)]
-enum Half {
-    LO,
-    HI,
+use crate::error::{parse_lines, Error, Result};
+use crate::solution::Solution;
+
+/// Seat (row, col) on the plane.
+pub type Seat = (usize, usize);
+
+/// Plane with given number of rows and columns. Boarding pass is a binary number: row bits as `F`/`B`
+/// (0/1) followed by column bits as `L`/`R`, with just enough bits to address every row and column.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SeatMap {
+    rows: usize,
+    cols: usize,
 }
 
-#[derive(Debug)]
-struct Ticket {
-    row: usize,
-    col: usize,
+// Number of bits needed to address `n` items.
+const fn bits(n: usize) -> usize {
+    (usize::BITS - (n - 1).leading_zeros()) as usize
 }
 
-impl Ticket {
-    fn new(row: usize, col: usize) -> Self {
-        Ticket {
-            row,
-            col
-        }
+const PLANE: SeatMap = SeatMap::new(128, 8);
+
+impl SeatMap {
+    pub const fn new(rows: usize, cols: usize) -> Self {
+        assert!(rows > 0 && cols > 0);
+        Self { rows, cols }
     }
 
-    fn id(&self) -> usize {
-        self.row * 8 + self.col
+    pub fn rows(&self) -> usize {
+        self.rows
     }
-}
 
-impl From<usize> for Ticket {
-    fn from(id: usize) -> Self {
-        let (row, col) = (id / 8, id % 8);
-        Self::new(row, col)
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn id(&self, (row, col): Seat) -> usize {
+        row * self.cols + col
+    }
+
+    pub fn seat(&self, id: usize) -> Seat {
+        (id / self.cols, id % self.cols)
+    }
+
+    pub fn decode(&self, pass: &str) -> Result<Seat> {
+        let (row_bits, col_bits) = (bits(self.rows), bits(self.cols));
+        let valid = pass.len() == row_bits + col_bits &&
+            pass.chars().take(row_bits).all(|c| c == 'F' || c == 'B') &&
+            pass.chars().skip(row_bits).all(|c| c == 'L' || c == 'R');
+        if !valid {
+            return Err(Error::new(pass, format!("expected {} of `F`/`B` followed by {} of `L`/`R`", row_bits, col_bits)));
+        }
+
+        let number = |s: &str| s.chars()
+            .fold(0, |acc, c| acc * 2 + if c == 'B' || c == 'R' { 1 } else { 0 });
+        let (row, col) = (number(&pass[..row_bits]), number(&pass[row_bits..]));
+        if row >= self.rows || col >= self.cols {
+            return Err(Error::new(pass, format!("seat {}x{} is outside of {}x{} plane", row, col, self.rows, self.cols)));
+        }
+        Ok((row, col))
     }
-}
 
-fn split(lo: usize, hi: usize, half: &Half) -> (usize, usize) {
-    assert!(lo <= hi);
-    if lo == hi {
-        (lo, hi)
-    } else {
-        let m = lo + (hi - lo) / 2;
-        match half {
-            Half::LO => (lo, m),
-            Half::HI => (m+1, hi)
+    /// Boarding pass for the seat, `None` if the seat is not on the plane.
+    pub fn encode(&self, (row, col): Seat) -> Option<String> {
+        if row >= self.rows || col >= self.cols {
+            return None;
         }
+        let bin = |n: usize, bits: usize, zero: char, one: char| (0..bits).rev()
+            .map(|i| if n >> i & 1 == 1 { one } else { zero })
+            .collect::<String>();
+        Some(bin(row, bits(self.rows), 'F', 'B') + &bin(col, bits(self.cols), 'L', 'R'))
     }
 }
 
-fn find(lo: usize, hi: usize, steps: &Vec<Half>) -> usize {
-    let (a, b) = steps.iter()
-        .fold((lo, hi), |(lo, hi), half| split(lo, hi, half));
-    assert_eq!(a, b);
-    a
+/// Every empty seat ID with both neighbouring IDs occupied, for sorted IDs of occupied seats.
+pub fn gaps(sorted: &[usize]) -> Vec<usize> {
+    sorted.windows(2)
+        .filter(|w| w[1] - w[0] == 2)
+        .map(|w| w[0] + 1)
+        .collect()
 }
 
-fn parse(line: &str) -> (Vec<Half>, Vec<Half>) {
-    let rows = line.chars()
-        .filter_map(|c| match c {
-            'F' => Some(Half::LO),
-            'B' => Some(Half::HI),
-            _ => None
-        })
-        .collect();
-
-    let cols = line.chars()
-        .skip_while(|c| *c == 'B' || *c == 'F')
-        .filter_map(|c| match c {
-            'L' => Some(Half::LO),
-            'R' => Some(Half::HI),
-            _ => None
-        })
-        .collect();
-
-    (rows, cols)
+fn input(text: &str, map: &SeatMap) -> Result<Vec<Seat>> {
+    parse_lines(text.lines(), |line| map.decode(line))
 }
 
-fn find_missing(sorted: &Vec<usize>) -> usize {
-    let (p, n) = sorted.iter().zip(sorted.iter().skip(1))
-        .find(|(p, n)| *n - *p > 1)
-        .map(|(p, n)| (*p, *n))
-        .unwrap();
+pub struct Day05;
 
-    assert_eq!(p + 1, n - 1);
-    p + 1
-}
+impl Solution for Day05 {
+    // Sorted seat IDs of all boarding passes
+    type Input = Vec<usize>;
+    type Part1 = usize;
+    type Part2 = usize;
 
-fn input() -> Vec<(Vec<Half>, Vec<Half>)> {
-    lines().into_iter()
-        .map(|line| parse(&line))
-        .collect()
-}
+    fn parse(text: &str) -> Result<Self::Input> {
+        let mut ids: Vec<usize> = input(text, &PLANE)?.into_iter()
+            .map(|seat| PLANE.id(seat))
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn part1(ids: &Self::Input) -> usize {
+        *ids.iter().max().unwrap()
+    }
 
-pub fn main() {
-    let tickets = input();
-    let mut ids: Vec<usize> = tickets.iter()
-        .map(|(rows, cols)| {
-            let row = find(0, 127, rows);
-            let col = find(0, 7, cols);
-            Ticket::new(row, col)
-        })
-        .map(|tkt| tkt.id())
-        .collect();
-
-    let max_id = ids.iter().max().unwrap();
-    println!("{}", *max_id);
-
-    ids.sort();
-    let missing_id = find_missing(&ids);
-    println!("{}", missing_id);
+    fn part2(ids: &Self::Input) -> usize {
+        gaps(ids)[0]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use Half::*;
 
     #[test]
-    fn test_split() {
-        assert_eq!(split(0, 127, &LO), (0, 63));
-        assert_eq!(split(0, 127, &HI), (64, 127));
-
-        assert_eq!(split(0, 63, &LO), (0, 31));
-        assert_eq!(split(0, 63, &HI), (32, 63));
+    fn test_decode() {
+        assert_eq!(PLANE.decode("FBFBBFFRLR").unwrap(), (44, 5));
+        assert_eq!(PLANE.id((44, 5)), 357);
+        assert_eq!(PLANE.seat(357), (44, 5));
+        assert_eq!(PLANE.id(PLANE.decode("BBFFBBFRLL").unwrap()), 820);
+
+        let map = SeatMap::new(5, 3);
+        assert_eq!(map.decode("BFFRL").unwrap(), (4, 2));
+        assert_eq!(map.decode("BFBRL").unwrap_err().reason(), "seat 5x2 is outside of 5x3 plane");
+        assert_eq!(SeatMap::new(1, 2).decode("R").unwrap(), (0, 1));
+    }
 
-        assert_eq!(split(32, 64, &LO), (32, 48));
-        assert_eq!(split(32, 64, &HI), (49, 64));
+    #[test]
+    fn test_decode_invalid() {
+        assert!(PLANE.decode("FBFBBFFRL").is_err());
+        assert!(PLANE.decode("FBFBBFFRLRR").is_err());
+        assert!(PLANE.decode("FBFBBFRRLR").is_err());
+        assert!(PLANE.decode("FBFBBFFRLX").is_err());
     }
 
     #[test]
-    fn test_find() {
-        // FBFBBFF
-        let steps = vec![LO, HI, LO, HI, HI, LO, LO];
-        assert_eq!(find(0, 127, &steps), 44);
+    fn test_encode() {
+        assert_eq!(PLANE.encode((44, 5)), Some("FBFBBFFRLR".to_string()));
+        assert_eq!(PLANE.encode((128, 0)), None);
+
+        let map = SeatMap::new(5, 3);
+        for id in 0..15 {
+            let seat = map.seat(id);
+            assert_eq!(map.decode(&map.encode(seat).unwrap()).unwrap(), seat);
+        }
     }
 
     #[test]
-    fn test_parse() {
-        let input = "FBFBBFFRLR";
-        let (rows, cols) = parse(input);
-        assert_eq!(rows, vec![LO, HI, LO, HI, HI, LO, LO]);
-        assert_eq!(cols, vec![HI, LO, HI]);
+    fn test_gaps() {
+        assert_eq!(gaps(&[1, 2, 4, 5, 8, 10, 11]), vec![3, 9]);
+        assert_eq!(gaps(&[1, 2]), vec![]);
     }
-}
\ No newline at end of file
+}
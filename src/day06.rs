@@ -1,49 +1,375 @@
-use std::collections::HashSet;
-use crate::utils::lines;
-
-fn any(lines: &Vec<String>) -> Vec<HashSet<char>> {
-    lines.split(|line| line.is_empty())
-        .map(|lines| {
-            let mut set = HashSet::new();
-            lines.iter()
-                .flat_map(|line| line.chars())
-                .for_each(|c| {
-                    set.insert(c);
-                });
-            set
-        })
-        .collect()
-}
-
-fn all(lines: &Vec<String>) -> Vec<HashSet<char>> {
-    lines.split(|line| line.is_empty())
-        .map(|lines| {
-            let sets: Vec<HashSet<char>> = lines.iter()
-                .map(|line| line.chars().collect::<HashSet<char>>())
-                .collect();
-
-            let head = sets[0].to_owned();
-            sets.iter().skip(1)
-                .fold(head, |acc, e|
-                    acc.intersection(e)
-                        .map(|e| *e)
-                        .collect())
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use crate::error::{parse_lines, Error, Result};
+use crate::input::Input;
+use crate::solution::Solution;
+
+fn parse(line: &str) -> Result<String> {
+    if line.chars().all(|c| c.is_ascii_lowercase()) {
+        Ok(line.to_string())
+    } else {
+        Err(Error::new(line, "expected questions `a` to `z`"))
+    }
+}
+
+// Set of questions `a` to `z` as bits.
+type Questions = u32;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    Num(usize),
+    Questions(Questions),
+    Open,
+    Close,
+    Percent,
+    Plus,
+    Minus,
+    And,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Cmp {
+    Exactly,
+    AtLeast,
+    AtMost,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Amount {
+    People(usize),
+    Percent(usize),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Expr {
+    /// Questions anyone in the group answered.
+    Union,
+    /// Questions everyone in the group answered.
+    Intersection,
+    /// Questions answered by given number or percentage of people in the group. Only questions
+    /// someone answered count, so `at most` never selects unanswered ones and `exactly 0` is empty.
+    Answered(Cmp, Amount),
+    Questions(Questions),
+    Plus(Box<Expr>, Box<Expr>),
+    Minus(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut it = query.chars().peekable();
+    while let Some(c) = it.next() {
+        let token = match c {
+            ' ' | '\t' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '%' => Token::Percent,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '&' => Token::And,
+            '[' => {
+                let mut questions = 0;
+                loop {
+                    match it.next() {
+                        Some(']') => break,
+                        Some(q) if q.is_ascii_lowercase() => questions |= 1 << (q as u8 - b'a'),
+                        _ => return Err(Error::new(query, "expected questions `a` to `z` in `[...]`")),
+                    }
+                }
+                Token::Questions(questions)
+            },
+            c if c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some(d) = it.next_if(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                }
+                Token::Num(digits.parse().map_err(|_| Error::new(query, "invalid number"))?)
+            },
+            c if c.is_ascii_alphabetic() => {
+                let mut word = c.to_string();
+                while let Some(w) = it.next_if(|w| w.is_ascii_alphabetic()) {
+                    word.push(w);
+                }
+                Token::Word(word.to_ascii_lowercase())
+            },
+            _ => return Err(Error::new(query, format!("unexpected character {:?}", c))),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    it: Peekable<IntoIter<Token>>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&mut self, expected: &str) -> Error {
+        let found = match self.it.peek() {
+            Some(token) => format!("{:?}", token),
+            None => "end of query".to_string(),
+        };
+        Error::new(self.query, format!("expected {}, found {}", expected, found))
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        self.it.next_if_eq(&token).is_some()
+    }
+
+    fn word(&mut self, words: &[&str]) -> bool {
+        self.it.next_if(|t| matches!(t, Token::Word(w) if words.contains(&w.as_str()))).is_some()
+    }
+
+    fn expect(&mut self, token: Token, expected: &str) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    // `<n> [%] [people|person]`
+    fn amount(&mut self) -> Result<Amount> {
+        let n = match self.it.next_if(|t| matches!(t, Token::Num(_))) {
+            Some(Token::Num(n)) => n,
+            _ => return Err(self.error("a number")),
+        };
+        let amount = if self.eat(Token::Percent) { Amount::Percent(n) } else { Amount::People(n) };
+        self.word(&["people", "person"]);
+        Ok(amount)
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        if self.word(&["union", "any", "anyone"]) {
+            Ok(Expr::Union)
+        } else if self.word(&["intersection", "all", "everyone"]) {
+            Ok(Expr::Intersection)
+        } else if self.word(&["exactly"]) {
+            Ok(Expr::Answered(Cmp::Exactly, self.amount()?))
+        } else if self.word(&["at"]) {
+            let cmp = if self.word(&["least"]) {
+                Cmp::AtLeast
+            } else if self.word(&["most"]) {
+                Cmp::AtMost
+            } else {
+                return Err(self.error("`least` or `most`"));
+            };
+            Ok(Expr::Answered(cmp, self.amount()?))
+        } else if let Some(Token::Questions(questions)) = self.it.next_if(|t| matches!(t, Token::Questions(_))) {
+            Ok(Expr::Questions(questions))
+        } else if self.eat(Token::Open) {
+            let expr = self.expr()?;
+            self.expect(Token::Close, "`)`")?;
+            Ok(expr)
+        } else {
+            Err(self.error("a set of questions"))
+        }
+    }
+
+    // Intersection binds tighter than union and difference.
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.atom()?;
+        while self.eat(Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.atom()?));
+        }
+        Ok(expr)
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        loop {
+            if self.eat(Token::Plus) {
+                expr = Expr::Plus(Box::new(expr), Box::new(self.and()?));
+            } else if self.eat(Token::Minus) {
+                expr = Expr::Minus(Box::new(expr), Box::new(self.and()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    // `count(<expr>)` or just `<expr>`.
+    fn query(&mut self) -> Result<Expr> {
+        let expr = if self.word(&["count"]) {
+            self.expect(Token::Open, "`(`")?;
+            let expr = self.expr()?;
+            self.expect(Token::Close, "`)`")?;
+            expr
+        } else {
+            self.expr()?
+        };
+        if self.it.peek().is_some() {
+            return Err(self.error("end of query"));
+        }
+        Ok(expr)
+    }
+}
+
+impl Expr {
+    /// Parse a query over a group, e.g. `count(exactly 2 people)`, `union - intersection`,
+    /// `at least 50%` or `(anyone & [abc]) + at most 1 person`. Sets combine with `+` (union),
+    /// `-` (difference) and `&` (intersection, binds tighter); `count(...)` around it is optional.
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parser = Parser { query, it: tokenize(query)?.into_iter().peekable() };
+        parser.query()
+    }
+
+    fn eval(&self, people: usize, tally: &[usize; 26]) -> Result<Questions> {
+        let select = |f: &dyn Fn(usize) -> bool| tally.iter().enumerate()
+            .filter(|(_, n)| f(**n))
+            .fold(0, |acc, (q, _)| acc | 1 << q);
+
+        Ok(match self {
+            Expr::Union => select(&|n| n > 0),
+            Expr::Intersection => select(&|n| n == people),
+            Expr::Answered(cmp, amount) => {
+                // Compare n / people with the percentage without rounding, n is at most people.
+                let (scale, k) = match amount {
+                    Amount::People(k) => (1, *k),
+                    Amount::Percent(k) => {
+                        let k = people.checked_mul(100).and(k.checked_mul(people))
+                            .ok_or_else(|| Error::new(&format!("{}%", k), "percentage too large for the group"))?;
+                        (100, k)
+                    },
+                };
+                select(&|n| n > 0 && match cmp {
+                    Cmp::Exactly => n * scale == k,
+                    Cmp::AtLeast => n * scale >= k,
+                    Cmp::AtMost => n * scale <= k,
+                })
+            },
+            Expr::Questions(questions) => *questions,
+            Expr::Plus(a, b) => a.eval(people, tally)? | b.eval(people, tally)?,
+            Expr::Minus(a, b) => a.eval(people, tally)? & !b.eval(people, tally)?,
+            Expr::And(a, b) => a.eval(people, tally)? & b.eval(people, tally)?,
         })
-        .collect()
+    }
+
+    /// Questions of the group (answers of each person) matching the expression.
+    pub fn select(&self, group: &[String]) -> Result<Vec<char>> {
+        let mut tally = [0; 26];
+        group.iter()
+            .flat_map(|person| person.bytes())
+            .for_each(|q| tally[(q - b'a') as usize] += 1);
+
+        let questions = self.eval(group.len(), &tally)?;
+        Ok((0..26u8)
+            .filter(|q| questions & 1 << q != 0)
+            .map(|q| (b'a' + q) as char)
+            .collect())
+    }
+
+    /// Sum of numbers of matching questions over all groups.
+    pub fn count(&self, groups: &[Vec<String>]) -> Result<usize> {
+        groups.iter()
+            .map(|group| self.select(group).map(|questions| questions.len()))
+            .sum()
+    }
 }
 
-pub fn main() {
-    let input = lines();
+pub struct Day06;
+
+impl Solution for Day06 {
+    type Input = Vec<Vec<String>>;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        Input::from(text).parse_paragraphs(|group| parse_lines(group.iter().map(|line| line.as_str()), parse))
+    }
+
+    // Only percentages can fail to evaluate.
+    fn part1(input: &Self::Input) -> usize {
+        Expr::Union.count(input).unwrap()
+    }
+
+    fn part2(input: &Self::Input) -> usize {
+        Expr::Intersection.count(input).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Vec<Vec<String>> {
+        Day06::parse(include_str!("../inputs/examples/day06-example.txt")).unwrap()
+    }
+
+    fn group(people: &[&str]) -> Vec<String> {
+        people.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_query() {
+        use Expr::*;
+
+        assert_eq!(Expr::parse("count(union)"), Ok(Union));
+        assert_eq!(Expr::parse("union - intersection"), Ok(Minus(Box::new(Union), Box::new(Intersection))));
+        assert_eq!(Expr::parse("count(exactly 2 people)"), Ok(Answered(Cmp::Exactly, Amount::People(2))));
+        assert_eq!(Expr::parse("at least 50%"), Ok(Answered(Cmp::AtLeast, Amount::Percent(50))));
+        assert_eq!(Expr::parse("any + all & [ab]"), Ok(Plus(
+            Box::new(Union),
+            Box::new(And(Box::new(Intersection), Box::new(Questions(0b11)))),
+        )));
+        assert_eq!(Expr::parse("(any + all) & [ab]"), Ok(And(
+            Box::new(Plus(Box::new(Union), Box::new(Intersection))),
+            Box::new(Questions(0b11)),
+        )));
+    }
+
+    #[test]
+    fn test_parse_query_invalid() {
+        let cases = vec![
+            "",
+            "count(union",
+            "count union",
+            "union union",
+            "at 2",
+            "exactly people",
+            "union - ",
+            "[aB]",
+            "union * all",
+        ];
+        for query in cases {
+            assert!(Expr::parse(query).is_err(), "{}", query);
+        }
+        assert_eq!(Expr::parse("at 2").unwrap_err().reason(), "expected `least` or `most`, found Num(2)");
+    }
+
+    #[test]
+    fn test_select() {
+        let g = group(&["abc", "ab", "ax", "a"]);
+        let select = |query: &str| Expr::parse(query).unwrap().select(&g).unwrap().into_iter().collect::<String>();
+
+        assert_eq!(select("union"), "abcx");
+        assert_eq!(select("intersection"), "a");
+        assert_eq!(select("union - intersection"), "bcx");
+        assert_eq!(select("exactly 2 people"), "b");
+        assert_eq!(select("exactly 1 person"), "cx");
+        assert_eq!(select("at least 50%"), "ab");
+        assert_eq!(select("at most 25%"), "cx");
+        assert_eq!(select("at most 1 person"), "cx");
+        assert_eq!(select("exactly 0 people"), "");
+        assert_eq!(select("(any & [abc]) + at most 1 person"), "abcx");
+        assert_eq!(select("[xyz] - any"), "yz");
+        assert_eq!(select("at least 1000%"), "");
 
-    let groups = any(&input);
-    let sum: usize = groups.iter()
-        .map(|set| set.len())
-        .sum();
-    println!("{}", sum);
+        let e = Expr::parse(&format!("any - at least {}%", usize::MAX)).unwrap().select(&g).unwrap_err();
+        assert_eq!((e.text(), e.reason()), (format!("{}%", usize::MAX).as_str(), "percentage too large for the group"));
+    }
 
-    let groups = all(&input);
-    let sum: usize = groups.iter()
-        .map(|set| set.len())
-        .sum();
-    println!("{}", sum);
+    #[test]
+    fn test_count() {
+        let groups = example();
+        assert_eq!(Expr::parse("count(union)").unwrap().count(&groups), Ok(11));
+        assert_eq!(Expr::parse("count(intersection)").unwrap().count(&groups), Ok(6));
+        assert_eq!(Expr::parse("count(union - intersection)").unwrap().count(&groups), Ok(5));
+        assert_eq!(Expr::parse("count(at least 1 person)").unwrap().count(&groups), Ok(11));
+        assert_eq!(Expr::parse("count(at most 1 person)").unwrap().count(&groups), Ok(9));
+        assert_eq!(Expr::parse("count(at least 0%)").unwrap().count(&groups), Ok(11));
+    }
 }
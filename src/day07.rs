@@ -1,99 +1,250 @@
-use std::collections::{HashMap, VecDeque, HashSet};
-use crate::utils::lines;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
 
 type Color = String;
 type Record = (usize, Color);
 type Capacity = Vec<Record>;
 
-fn input(lines: &Vec<String>) -> HashMap<Color, Capacity> {
-    lines.iter()
-        .map(|line| parse(line))
-        .fold(HashMap::new(), |mut acc, (color, capacity)| {
-            acc.insert(color, capacity);
-            acc
-        })
+const GOLD: &str = "shiny gold";
+
+fn input<'a, I: IntoIterator<Item = &'a str>>(lines: I) -> Result<Bags> {
+    Bags::new(parse_lines(lines, parse)?)
 }
 
-fn parse(line: &str) -> (Color, Capacity) {
-    fn parse_capacity_record(remaining: &str) -> Record {
-        let words: Vec<&str> = remaining.split(" ").collect();
-        let count: usize = words[0].parse().unwrap();
+fn parse(line: &str) -> Result<(Color, Capacity)> {
+    fn parse_capacity_record(remaining: &str) -> Result<Record> {
+        let words: Vec<&str> = remaining.split(' ').collect();
+        if words.len() < 3 {
+            return Err(Error::new(remaining, "expected `N adjective color bags`"));
+        }
+        let count: usize = number(words[0])?;
         let color = words[1].to_string() + " " + words[2];
-        (count, color)
+        Ok((count, color))
     }
 
     let mut mid = line.split(" bags contain ");
     let color = mid.next().unwrap().to_string();
-    let remaining = mid.next().unwrap();
+    let remaining = mid.next()
+        .ok_or_else(|| Error::new(line, "expected `<color> bags contain ...`"))?;
 
     if remaining.starts_with("no") {
-        (color, vec![])
+        Ok((color, vec![]))
     } else {
-        if remaining.contains(",") {
-            let capacity: Vec<Record> = remaining.split(", ")
-                .map(|sub_line| parse_capacity_record(sub_line))
+        let capacity = remaining.split(", ")
+            .map(parse_capacity_record)
+            .collect::<Result<Vec<Record>>>()?;
+        Ok((color, capacity))
+    }
+}
+
+/// Graph of bag rules: bags are numbered in order of their rules (bags only mentioned inside other
+/// bags come last and are empty), with edges from each bag to the bags it directly contains.
+#[derive(Debug, Clone)]
+pub struct Bags {
+    colors: Vec<Color>,
+    ids: HashMap<Color, usize>,
+    contents: Vec<Vec<(usize, usize)>>,
+    parents: Vec<Vec<usize>>,
+    // Outermost bags first.
+    order: Vec<usize>,
+    // Number of bags inside of each bag.
+    nested: Vec<usize>,
+}
+
+impl Bags {
+    /// Build the graph from rules in the order of input lines, failing on a duplicate rule,
+    /// a bag that (eventually) contains itself, or one with more bags inside than fit in `usize`.
+    pub fn new(rules: Vec<(Color, Capacity)>) -> Result<Self> {
+        let mut colors: Vec<Color> = Vec::new();
+        let mut ids: HashMap<Color, usize> = HashMap::new();
+        for (i, (color, _)) in rules.iter().enumerate() {
+            if ids.insert(color.clone(), i).is_some() {
+                return Err(Error::new(color, "duplicate rule").at(i + 1));
+            }
+            colors.push(color.clone());
+        }
+
+        let mut contents = Vec::new();
+        for (_, capacity) in &rules {
+            let inner = capacity.iter()
+                .map(|(n, color)| {
+                    let id = *ids.entry(color.clone()).or_insert_with(|| {
+                        colors.push(color.clone());
+                        colors.len() - 1
+                    });
+                    (*n, id)
+                })
                 .collect();
-            (color, capacity)
-        } else {
-            (color, vec![parse_capacity_record(remaining)])
+            contents.push(inner);
         }
+        contents.resize(colors.len(), Vec::new());
+
+        let mut parents = vec![Vec::new(); colors.len()];
+        for (outer, inner) in contents.iter().enumerate() {
+            for (_, id) in inner {
+                parents[*id].push(outer);
+            }
+        }
+
+        let mut bags = Self { colors, ids, contents, parents, order: Vec::new(), nested: Vec::new() };
+        bags.order = bags.sort()?;
+        bags.nested = vec![0; bags.colors.len()];
+        for id in bags.order.iter().rev() {
+            let mut total: usize = 0;
+            for (n, inner) in &bags.contents[*id] {
+                total = bags.nested[*inner].checked_add(1)
+                    .and_then(|each| each.checked_mul(*n))
+                    .and_then(|inside| total.checked_add(inside))
+                    .ok_or_else(|| Error::new(&bags.colors[*id], "too many bags inside").at(id + 1))?;
+            }
+            bags.nested[*id] = total;
+        }
+        Ok(bags)
     }
-}
 
-fn build_reverse_index(index: &HashMap<Color, Capacity>) -> HashMap<Color, Vec<Color>> {
-    index.iter()
-        .flat_map(|(color, cap)|
-            cap.iter().map(move |c| (color.clone(), c.1.clone())))
-        .fold(HashMap::new(), |mut acc, (parent, child)| {
-            if !acc.contains_key(&child) {
-                acc.insert(child.clone(), Vec::new());
+    // Depth-first topological sort, reporting the first cycle found with the line of the rule closing it.
+    fn sort(&self) -> Result<Vec<usize>> {
+        #[derive(Clone, Copy, Eq, PartialEq)]
+        enum Mark { New, Open, Done }
+
+        let mut marks = vec![Mark::New; self.colors.len()];
+        let mut order = Vec::with_capacity(self.colors.len());
+        for root in 0..self.colors.len() {
+            if marks[root] != Mark::New {
+                continue;
             }
-            acc.get_mut(&child).unwrap().push(parent.to_owned());
-            acc
-        })
-}
+            marks[root] = Mark::Open;
+            let mut stack = vec![(root, 0)];
+            while let Some((id, edge)) = stack.last_mut() {
+                let id = *id;
+                match self.contents[id].get(*edge) {
+                    Some((_, inner)) => {
+                        *edge += 1;
+                        match marks[*inner] {
+                            Mark::New => {
+                                marks[*inner] = Mark::Open;
+                                stack.push((*inner, 0));
+                            },
+                            Mark::Open => {
+                                let from = stack.iter().position(|(id, _)| id == inner).unwrap();
+                                let cycle = stack[from..].iter()
+                                    .map(|(id, _)| self.colors[*id].as_str())
+                                    .chain(Some(self.colors[*inner].as_str()))
+                                    .collect::<Vec<_>>();
+                                let reason = format!("bag contains itself: {}", cycle.join(" -> "));
+                                return Err(Error::new(&self.colors[id], reason).at(id + 1));
+                            },
+                            Mark::Done => (),
+                        }
+                    },
+                    None => {
+                        marks[id] = Mark::Done;
+                        order.push(id);
+                        stack.pop();
+                    },
+                }
+            }
+        }
+        order.reverse();
+        Ok(order)
+    }
 
-fn bfs(reverse_index: &HashMap<Color, Vec<Color>>, color: &str) -> usize {
-    let mut queue: VecDeque<Color> = VecDeque::new();
-    reverse_index.get(color).unwrap().iter()
-        .for_each(|color| queue.push_back(color.clone()));
-
-    let mut seen: HashSet<Color> = HashSet::new();
-    while !queue.is_empty() {
-        let color = queue.pop_front().unwrap();
-        seen.insert(color.clone());
-
-        reverse_index.get(&color).unwrap_or(&vec![])
-            .iter()
-            .for_each(|next| {
-                if !seen.contains(next) {
-                    queue.push_back(next.clone());
+    fn id(&self, color: &str) -> Result<usize> {
+        self.ids.get(color).copied()
+            .ok_or_else(|| Error::new(color, "unknown colour"))
+    }
+
+    /// Bags that eventually contain the bag, in order of their rules.
+    pub fn containers(&self, color: &str) -> Result<Vec<&str>> {
+        let mut seen: HashSet<usize> = HashSet::new();
+        let mut queue: VecDeque<usize> = VecDeque::from(vec![self.id(color)?]);
+        while let Some(id) = queue.pop_front() {
+            for parent in &self.parents[id] {
+                if seen.insert(*parent) {
+                    queue.push_back(*parent);
                 }
-            });
+            }
+        }
+
+        let mut ids = seen.into_iter().collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids.into_iter().map(|id| self.colors[id].as_str()).collect())
     }
 
-    seen.len()
-}
+    /// Total number of bags inside the bag.
+    pub fn nested(&self, color: &str) -> Result<usize> {
+        self.id(color).map(|id| self.nested[id])
+    }
+
+    /// Every chain of bags from `outer` down to `inner`, each directly containing the next one.
+    pub fn paths(&self, outer: &str, inner: &str) -> Result<Vec<Vec<&str>>> {
+        // Only bags leading to the target are visited.
+        fn walk<'a>(bags: &'a Bags, id: usize, target: usize, leads: &HashSet<&str>, path: &mut Vec<&'a str>, paths: &mut Vec<Vec<&'a str>>) {
+            path.push(&bags.colors[id]);
+            if id == target {
+                paths.push(path.clone());
+            } else {
+                for (_, next) in &bags.contents[id] {
+                    if *next == target || leads.contains(bags.colors[*next].as_str()) {
+                        walk(bags, *next, target, leads, path, paths);
+                    }
+                }
+            }
+            path.pop();
+        }
+
+        let (from, to) = (self.id(outer)?, self.id(inner)?);
+        let leads = self.containers(inner)?.into_iter().collect();
+        let mut paths = Vec::new();
+        walk(self, from, to, &leads, &mut Vec::new(), &mut paths);
+        Ok(paths)
+    }
+
+    /// All bags, each one before every bag it contains.
+    pub fn topological(&self) -> Vec<&str> {
+        self.order.iter().map(|id| self.colors[*id].as_str()).collect()
+    }
 
-// `init` defines if enclosing (wrapper) bag is included in total count:
-// top-level bag is not included (0), but all others are included (1).
-fn dfs(index: &HashMap<Color, Capacity>, color: &str, init: usize) -> usize {
-    assert!(init == 0 || init == 1);
-    let capacity = index.get(color).unwrap();
-    capacity.iter().fold(init, |acc, (n, color)| {
-        acc + n * dfs(index, color, 1)
-    })
+    /// Graphviz DOT graph with an edge from each bag to every bag it contains, labeled with the count.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bags {\n");
+        for color in &self.colors {
+            writeln!(dot, "    {:?};", color).unwrap();
+        }
+        for (outer, inner) in self.contents.iter().enumerate() {
+            for (n, id) in inner {
+                writeln!(dot, "    {:?} -> {:?} [label={}];", self.colors[outer], self.colors[*id], n).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
 }
 
-pub fn main() {
-    let index = input(&lines());
-    let reverse = build_reverse_index(&index);
+pub struct Day07;
 
-    let count = bfs(&reverse, "shiny gold");
-    println!("{}", count);
+impl Solution for Day07 {
+    type Input = Bags;
+    type Part1 = usize;
+    type Part2 = usize;
 
-    let count = dfs(&index, "shiny gold", 0);
-    println!("{}", count);
+    fn parse(text: &str) -> Result<Self::Input> {
+        let bags = input(text.lines())?;
+        bags.id(GOLD)?;
+        Ok(bags)
+    }
+
+    // Parsing made sure the shiny gold bag is known.
+    fn part1(bags: &Self::Input) -> usize {
+        bags.containers(GOLD).unwrap().len()
+    }
+
+    fn part2(bags: &Self::Input) -> usize {
+        bags.nested(GOLD).unwrap()
+    }
 }
 
 #[cfg(test)]
@@ -107,7 +258,7 @@ mod tests {
             4 dotted indigo bags, \
             3 faded orange bags, \
             5 plaid crimson bags.";
-        let (color, capacity) = parse(line);
+        let (color, capacity) = parse(line).unwrap();
 
         assert_eq!(color, "faded yellow");
         assert_eq!(capacity, vec![
@@ -121,7 +272,7 @@ mod tests {
     #[test]
     fn test_parse_one() {
         let line = "muted fuchsia bags contain 3 shiny bronze bags.";
-        let (color, capacity) = parse(line);
+        let (color, capacity) = parse(line).unwrap();
 
         assert_eq!(color, "muted fuchsia");
         assert_eq!(capacity, vec![(3, "shiny bronze".to_string())]);
@@ -130,50 +281,115 @@ mod tests {
     #[test]
     fn test_parse_none() {
         let line = "plaid gray bags contain no other bags.";
-        let (color, capacity) = parse(line);
+        let (color, capacity) = parse(line).unwrap();
 
         assert_eq!(color, "plaid gray");
         assert_eq!(capacity, vec![]);
     }
 
     #[test]
-    fn test_bfs() {
-        let lines = vec![
-            "light red bags contain 1 bright white bag, 2 muted yellow bags.",
-            "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
-            "bright white bags contain 1 shiny gold bag.",
-            "muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.",
-            "shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.",
-            "dark olive bags contain 3 faded blue bags, 4 dotted black bags.",
-            "vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.",
-            "faded blue bags contain no other bags.",
-            "dotted black bags contain no other bags.",
-        ];
+    fn test_parse_invalid() {
+        assert!(parse("plaid gray bags").is_err());
+        assert!(parse("plaid gray bags contain x shiny gold bags.").is_err());
+        assert!(parse("plaid gray bags contain 2 bags.").is_err());
+    }
+
+    #[test]
+    fn test_containers() {
+        let bags = Day07::parse(include_str!("../inputs/examples/day07-example.txt")).unwrap();
+
+        assert_eq!(bags.containers("shiny gold"), Ok(vec!["light red", "dark orange", "bright white", "muted yellow"]));
+        assert_eq!(bags.containers("light red"), Ok(vec![]));
+
+        let e = bags.containers("pale green").unwrap_err();
+        assert_eq!((e.text(), e.reason()), ("pale green", "unknown colour"));
+    }
 
-        let strings: Vec<String> = lines.into_iter().map(|s| s.to_string()).collect();
-        let index = input(&strings);
-        let reverse_index = build_reverse_index(&index);
+    #[test]
+    fn test_nested() {
+        let bags = Day07::parse(include_str!("../inputs/examples/day07-nested.txt")).unwrap();
+
+        assert_eq!(bags.nested("shiny gold"), Ok(126));
+        assert_eq!(bags.nested("dark blue"), Ok(2));
+        assert_eq!(bags.nested("dark violet"), Ok(0));
+        assert!(bags.nested("pale green").is_err());
 
-        let count = bfs(&reverse_index, "shiny gold");
-        assert_eq!(count, 4);
+        let rules = vec![
+            "light red bags contain 4294967295 dark blue bags.",
+            "dark blue bags contain 4294967296 dark violet bags.",
+        ];
+        assert_eq!(input(rules.clone()).unwrap().nested("light red"), Ok(usize::MAX));
+        let rules = vec!["light red bags contain 4294967296 dark blue bags.", rules[1]];
+        let e = input(rules).unwrap_err();
+        assert_eq!((e.line(), e.text(), e.reason()), (Some(1), "light red", "too many bags inside"));
+        assert_eq!(bags.containers("shiny gold"), Ok(vec![]));
+
+        let e = Day07::parse("dark blue bags contain 2 dark violet bags.\n").unwrap_err();
+        assert_eq!((e.text(), e.reason()), ("shiny gold", "unknown colour"));
     }
 
     #[test]
-    fn test_dfs() {
+    fn test_cycle() {
         let lines = vec![
-            "shiny gold bags contain 2 dark red bags.",
-            "dark red bags contain 2 dark orange bags.",
-            "dark orange bags contain 2 dark yellow bags.",
-            "dark yellow bags contain 2 dark green bags.",
-            "dark green bags contain 2 dark blue bags.",
-            "dark blue bags contain 2 dark violet bags.",
-            "dark violet bags contain no other bags.",
+            "light red bags contain 1 bright white bag.",
+            "bright white bags contain 2 muted yellow bags, 1 faded blue bag.",
+            "muted yellow bags contain 3 light red bags.",
         ];
+        let e = input(lines).unwrap_err();
+        assert_eq!(e.line(), Some(3));
+        assert_eq!(e.reason(), "bag contains itself: light red -> bright white -> muted yellow -> light red");
+
+        let e = input(vec!["shiny gold bags contain 1 shiny gold bag."]).unwrap_err();
+        assert_eq!(e.reason(), "bag contains itself: shiny gold -> shiny gold");
 
-        let strings: Vec<String> = lines.into_iter().map(|s| s.to_string()).collect();
-        let index = input(&strings);
+        let e = input(vec!["shiny gold bags contain no other bags.", "shiny gold bags contain no other bags."]).unwrap_err();
+        assert_eq!((e.line(), e.reason()), (Some(2), "duplicate rule"));
+    }
+
+    #[test]
+    fn test_paths() {
+        let bags = Day07::parse(include_str!("../inputs/examples/day07-example.txt")).unwrap();
+
+        assert_eq!(bags.paths("light red", "shiny gold"), Ok(vec![
+            vec!["light red", "bright white", "shiny gold"],
+            vec!["light red", "muted yellow", "shiny gold"],
+        ]));
+        assert_eq!(bags.paths("dark orange", "faded blue").unwrap().len(), 5);
+        assert_eq!(bags.paths("faded blue", "shiny gold"), Ok(vec![]));
+        assert_eq!(bags.paths("shiny gold", "shiny gold"), Ok(vec![vec!["shiny gold"]]));
+        assert!(bags.paths("light red", "pale green").is_err());
+        assert!(bags.paths("pale green", "shiny gold").is_err());
+    }
 
-        let count = dfs(&index, "shiny gold", 0);
-        assert_eq!(count, 126);
+    #[test]
+    fn test_topological() {
+        let bags = Day07::parse(include_str!("../inputs/examples/day07-example.txt")).unwrap();
+        let order = bags.topological();
+        assert_eq!(order.len(), 9);
+
+        let position = |color: &str| order.iter().position(|c| *c == color).unwrap();
+        for color in &order {
+            for path in bags.paths(color, "faded blue").unwrap() {
+                assert!(path.windows(2).all(|w| position(w[0]) < position(w[1])));
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let bags = input(vec![
+            "bright white bags contain 1 shiny gold bag.",
+            "shiny gold bags contain 2 dark olive bags.",
+        ]).unwrap();
+        let expected = [
+            "digraph bags {",
+            "    \"bright white\";",
+            "    \"shiny gold\";",
+            "    \"dark olive\";",
+            "    \"bright white\" -> \"shiny gold\" [label=1];",
+            "    \"shiny gold\" -> \"dark olive\" [label=2];",
+            "}",
+        ];
+        assert_eq!(bags.to_dot(), expected.join("\n") + "\n");
     }
 }
@@ -1,73 +1,210 @@
-use crate::utils::lines;
-use std::collections::HashSet;
+#![allow(clippy::ptr_arg)]
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::Peekable;
+
+use crate::error::{number, Error, Result};
+use crate::solution::Solution;
+
+/// Register index, the accumulator is register 0.
+pub type Reg = usize;
+
+pub const ACC: Reg = 0;
+pub const REGISTERS: [&str; 5] = ["acc", "a", "b", "c", "d"];
+
+fn register(name: &str) -> Option<Reg> {
+    REGISTERS.iter().position(|r| *r == name)
+}
+
+/// Operand: a number or a value of a register.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Arg {
+    Num(i64),
+    Reg(Reg),
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arg::Num(n) => write!(f, "{:+}", n),
+            Arg::Reg(r) => write!(f, "{}", REGISTERS[*r]),
+        }
+    }
+}
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-enum Op {
+pub enum Op {
     Acc(i64),
     Jmp(i64),
     Nop(i64),
+    Add(Reg, Arg),
+    Mul(Reg, Arg),
+    Set(Reg, Arg),
+    /// Jump by the offset if the register is zero.
+    Jz(Reg, i64),
+    /// Jump by the offset if the register is not zero.
+    Jnz(Reg, i64),
+    /// Terminate the program.
+    Halt,
 }
 
-struct Run {
+impl Op {
+    // Register the instruction writes to, the accumulator for those that don't write at all.
+    fn target(&self) -> Reg {
+        match self {
+            Op::Add(r, _) | Op::Mul(r, _) | Op::Set(r, _) => *r,
+            _ => ACC,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Acc(arg) => write!(f, "acc {:+}", arg),
+            Op::Jmp(arg) => write!(f, "jmp {:+}", arg),
+            Op::Nop(arg) => write!(f, "nop {:+}", arg),
+            Op::Add(r, arg) => write!(f, "add {} {}", REGISTERS[*r], arg),
+            Op::Mul(r, arg) => write!(f, "mul {} {}", REGISTERS[*r], arg),
+            Op::Set(r, arg) => write!(f, "set {} {}", REGISTERS[*r], arg),
+            Op::Jz(r, arg) => write!(f, "jz {} {:+}", REGISTERS[*r], arg),
+            Op::Jnz(r, arg) => write!(f, "jnz {} {:+}", REGISTERS[*r], arg),
+            Op::Halt => write!(f, "halt"),
+        }
+    }
+}
+
+pub struct Run {
     code: Vec<Op>,
-    acc: i64,
+    regs: [i64; REGISTERS.len()],
     op: usize,
     done: bool,
+    fault: bool,
 }
 
 impl Run {
-    fn new(code: &Vec<Op>) -> Self {
+    pub fn new(code: &[Op]) -> Self {
         Self {
-            code: code.clone(),
-            acc: 0,
+            code: code.to_vec(),
+            regs: [0; REGISTERS.len()],
             op: 0,
-            done: false,
+            done: code.is_empty(),
+            fault: false,
         }
     }
 
-    fn acc(&self) -> i64 {
-        self.acc
+    pub fn acc(&self) -> i64 {
+        self.regs[ACC]
     }
 
-    fn op(&self) -> usize {
+    pub fn reg(&self, r: Reg) -> i64 {
+        self.regs[r]
+    }
+
+    pub fn op(&self) -> usize {
         self.op
     }
 
-    fn terminated(&self) -> bool {
+    pub fn terminated(&self) -> bool {
         self.done
     }
 
-    // Run single step, return if program is still running.
+    /// The run stopped on a jump out of the program, `op` is the index of the jump.
+    pub fn faulted(&self) -> bool {
+        self.fault
+    }
+
+    fn value(&self, arg: Arg) -> i64 {
+        match arg {
+            Arg::Num(n) => n,
+            Arg::Reg(r) => self.regs[r],
+        }
+    }
+
+    fn jump(&mut self, arg: i64) {
+        match target(&self.code, self.op, arg) {
+            Some(to) => self.op = to,
+            None => {
+                self.done = true;
+                self.fault = true;
+            },
+        }
+    }
+
+    // Run single step, return if program is still running. Register arithmetic wraps around on
+    // overflow.
     //      "The program is supposed to terminate by attempting to execute
     //      an instruction immediately after the last instruction in the file."
-    fn step(&mut self) {
+    pub fn step(&mut self) {
         if self.done {
             return;
         }
 
-        let op = self.code.get(self.op).unwrap();
-        match op {
-            Op::Jmp(arg) => {
-                let offset = arg.abs() as usize;
-                if *arg < 0 {
-                    self.op -= offset;
-                } else {
-                    self.op += offset;
-                }
+        let op = match self.code.get(self.op) {
+            Some(op) => op.clone(),
+            None => {
+                self.done = true;
+                return;
             },
+        };
+        match op {
+            Op::Jmp(arg) => self.jump(arg),
             Op::Acc(arg) => {
-                self.acc += *arg;
+                self.regs[ACC] = self.regs[ACC].wrapping_add(arg);
                 self.op += 1;
             }
             Op::Nop(_) => {
                 self.op += 1;
             }
+            Op::Add(r, arg) => {
+                self.regs[r] = self.regs[r].wrapping_add(self.value(arg));
+                self.op += 1;
+            },
+            Op::Mul(r, arg) => {
+                self.regs[r] = self.regs[r].wrapping_mul(self.value(arg));
+                self.op += 1;
+            },
+            Op::Set(r, arg) => {
+                self.regs[r] = self.value(arg);
+                self.op += 1;
+            },
+            Op::Jz(r, arg) | Op::Jnz(r, arg) => {
+                if (self.regs[r] == 0) == matches!(op, Op::Jz(..)) {
+                    self.jump(arg);
+                } else {
+                    self.op += 1;
+                }
+            },
+            Op::Halt => {
+                self.done = true;
+                return;
+            },
         }
 
         if self.op == self.code.len() {
             self.done = true;
         }
     }
+
+    // Accumulator at termination when continuing from `op` instead of the current instruction,
+    // `None` if the run gets to an instruction `revisit` reports as visited or jumps out of the
+    // program. Leaves the state as it was.
+    fn resume<F: FnMut(usize) -> bool>(&mut self, op: usize, mut revisit: F) -> Option<i64> {
+        let (regs, at) = (self.regs, self.op);
+        self.op = op;
+        self.done = op == self.code.len();
+        while !self.done && !revisit(self.op) {
+            self.step();
+        }
+        let acc = (self.done && !self.fault).then(|| self.acc());
+        self.regs = regs;
+        self.op = at;
+        self.done = false;
+        self.fault = false;
+        acc
+    }
 }
 
 // Run the code and terminate if an infinite loop is detected (op is revisited)
@@ -87,19 +224,398 @@ fn exec(code: &Vec<Op>) -> Run {
     run
 }
 
-// Pick candidates for jmp <-> nop swap (indices).
-fn pick(code: &Vec<Op>) -> Vec<usize> {
-    code.iter().enumerate()
-        .filter_map(|(i, e)| {
-            match e {
-                Op::Jmp(_) | Op::Nop(_) => Some(i),
-                _ => None
+/// Watch expression over the state of the run: registers, `op` (index of the next instruction)
+/// and integers, combined with `+`, `-`, `*` and parentheses. Wraps around on overflow, as the
+/// registers do.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Expr {
+    Reg(Reg),
+    Op,
+    Num(i64),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = text.replace('(', " ( ").replace(')', " ) ")
+            .replace('+', " + ").replace('-', " - ").replace('*', " * ");
+        let mut it = tokens.split_whitespace().peekable();
+        let expr = Self::sum(text, &mut it)?;
+        match it.next() {
+            None => Ok(expr),
+            Some(token) => Err(Error::new(text, format!("unexpected `{}`", token))),
+        }
+    }
+
+    fn sum<'a, I: Iterator<Item = &'a str>>(text: &str, it: &mut Peekable<I>) -> Result<Self> {
+        let mut expr = Self::product(text, it)?;
+        while let Some(op) = it.next_if(|t| *t == "+" || *t == "-") {
+            let rhs = Box::new(Self::product(text, it)?);
+            expr = if op == "+" { Expr::Add(Box::new(expr), rhs) } else { Expr::Sub(Box::new(expr), rhs) };
+        }
+        Ok(expr)
+    }
+
+    fn product<'a, I: Iterator<Item = &'a str>>(text: &str, it: &mut Peekable<I>) -> Result<Self> {
+        let mut expr = Self::atom(text, it)?;
+        while it.next_if_eq(&"*").is_some() {
+            expr = Expr::Mul(Box::new(expr), Box::new(Self::atom(text, it)?));
+        }
+        Ok(expr)
+    }
+
+    fn atom<'a, I: Iterator<Item = &'a str>>(text: &str, it: &mut Peekable<I>) -> Result<Self> {
+        match it.next() {
+            Some("op") => Ok(Expr::Op),
+            Some("-") => Ok(Expr::Sub(Box::new(Expr::Num(0)), Box::new(Self::atom(text, it)?))),
+            Some("(") => {
+                let expr = Self::sum(text, it)?;
+                match it.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err(Error::new(text, "expected `)`")),
+                }
+            },
+            Some(token) if token.starts_with(|c: char| c.is_ascii_digit()) => number(token).map(Expr::Num),
+            Some(token) => register(token)
+                .map(Expr::Reg)
+                .ok_or_else(|| Error::new(text, format!("unexpected `{}`", token))),
+            None => Err(Error::new(text, "unexpected end of expression")),
+        }
+    }
+
+    pub fn eval(&self, run: &Run) -> i64 {
+        match self {
+            Expr::Reg(r) => run.reg(*r),
+            Expr::Op => run.op() as i64,
+            Expr::Num(n) => *n,
+            Expr::Add(a, b) => a.eval(run).wrapping_add(b.eval(run)),
+            Expr::Sub(a, b) => a.eval(run).wrapping_sub(b.eval(run)),
+            Expr::Mul(a, b) => a.eval(run).wrapping_mul(b.eval(run)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Breakpoint {
+    /// Stop before executing the instruction at the index.
+    Op(usize),
+    /// Stop once the accumulator is set to the value.
+    Acc(i64),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Op(op) => write!(f, "{}", op),
+            Breakpoint::Acc(acc) => write!(f, "acc={}", acc),
+        }
+    }
+}
+
+/// Executed instruction, with the register it writes to (the accumulator for instructions that
+/// don't write) before and after it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Trace {
+    pub op: usize,
+    pub code: Op,
+    pub reg: Reg,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:4}: {:<10} {} {} -> {}", self.op, self.code.to_string(), REGISTERS[self.reg], self.before, self.after)
+    }
+}
+
+/// Why the debugger stopped.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Stop {
+    Breakpoint(Breakpoint),
+    /// Watch expression (by index) changed its value from first to second.
+    Watch(usize, i64, i64),
+    /// Next instruction was executed before, the run would loop forever.
+    Loop(usize),
+    Terminated,
+    /// Step limit reached.
+    Limit,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Breakpoint(breakpoint) => write!(f, "breakpoint {}", breakpoint),
+            Stop::Watch(i, before, after) => write!(f, "watch {}: {} -> {}", i, before, after),
+            Stop::Loop(op) => write!(f, "loop back to {}", op),
+            Stop::Terminated => write!(f, "terminated"),
+            Stop::Limit => write!(f, "step limit"),
+        }
+    }
+}
+
+/// Instructions executed before entering an infinite loop and the instructions of the loop.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Loop {
+    pub prefix: Vec<usize>,
+    pub cycle: Vec<(usize, Op)>,
+    pub acc: i64,
+}
+
+impl fmt::Display for Loop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "loop of {} instructions entered after {} steps with acc {}:",
+                 self.cycle.len(), self.prefix.len(), self.acc)?;
+        for (op, code) in &self.cycle {
+            writeln!(f, "{:4}: {}", op, code)?;
+        }
+        Ok(())
+    }
+}
+
+/// Debugger over the run: every step is recorded in the trace, which also serves as the history
+/// for stepping back.
+pub struct Debugger {
+    run: Run,
+    trace: Vec<Trace>,
+    visits: Vec<usize>,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<(Expr, i64)>,
+}
+
+impl Debugger {
+    pub fn new(code: &[Op]) -> Self {
+        Self {
+            run: Run::new(code),
+            trace: Vec::new(),
+            visits: vec![0; code.len()],
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+        }
+    }
+
+    pub fn run(&self) -> &Run {
+        &self.run
+    }
+
+    pub fn trace(&self) -> &[Trace] {
+        &self.trace
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.retain(|b| *b != breakpoint);
+    }
+
+    /// Add a watch expression, returns its index.
+    pub fn watch(&mut self, expr: Expr) -> usize {
+        let value = expr.eval(&self.run);
+        self.watches.push((expr, value));
+        self.watches.len() - 1
+    }
+
+    /// Current values of the watch expressions.
+    pub fn watches(&self) -> Vec<i64> {
+        self.watches.iter().map(|(expr, _)| expr.eval(&self.run)).collect()
+    }
+
+    /// Execute a single instruction, returns false if the run already terminated.
+    pub fn step(&mut self) -> bool {
+        if self.run.terminated() {
+            return false;
+        }
+        let op = self.run.op();
+        let code = self.run.code[op].clone();
+        let reg = code.target();
+        let before = self.run.reg(reg);
+        self.run.step();
+        self.visits[op] += 1;
+        self.trace.push(Trace { op, code, reg, before, after: self.run.reg(reg) });
+        true
+    }
+
+    /// Undo the last step, returns false if there is nothing to undo.
+    pub fn back(&mut self) -> bool {
+        match self.trace.pop() {
+            Some(trace) => {
+                self.visits[trace.op] -= 1;
+                self.run.op = trace.op;
+                self.run.regs[trace.reg] = trace.before;
+                self.run.done = false;
+                self.run.fault = false;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Step until a breakpoint, a change of a watch expression, the first revisit of an instruction,
+    /// termination, or the `limit` of steps. Always makes at least one step, so that it continues
+    /// past the breakpoint it stopped at last time.
+    pub fn cont(&mut self, limit: usize) -> Stop {
+        let looped = self.visits.iter().any(|n| *n > 1);
+        for _ in 0..limit {
+            if !self.step() {
+                return Stop::Terminated;
+            }
+
+            let mut changed = None;
+            for (i, (expr, last)) in self.watches.iter_mut().enumerate() {
+                let value = expr.eval(&self.run);
+                if value != *last {
+                    changed = changed.or(Some(Stop::Watch(i, *last, value)));
+                    *last = value;
+                }
+            }
+            if let Some(stop) = changed {
+                return stop;
             }
-        })
+            if self.run.terminated() {
+                return Stop::Terminated;
+            }
+
+            let (op, acc) = (self.run.op(), self.run.acc());
+            let set = self.trace.last().is_some_and(|t| t.reg == ACC && t.before != acc);
+            let hit = self.breakpoints.iter()
+                .find(|b| **b == Breakpoint::Op(op) || set && **b == Breakpoint::Acc(acc));
+            if let Some(breakpoint) = hit {
+                return Stop::Breakpoint(*breakpoint);
+            }
+            if !looped && self.visits[op] > 0 {
+                return Stop::Loop(op);
+            }
+        }
+        Stop::Limit
+    }
+
+    /// The loop, if the trace entered one: everything from the first execution of the first
+    /// revisited instruction up to its second execution.
+    pub fn loop_report(&self) -> Option<Loop> {
+        let mut first = vec![None; self.visits.len()];
+        for (i, trace) in self.trace.iter().enumerate() {
+            if let Some(start) = first[trace.op] {
+                return Some(Loop {
+                    prefix: self.trace[..start].iter().map(|t| t.op).collect(),
+                    cycle: self.trace[start..i].iter().map(|t| (t.op, t.code.clone())).collect(),
+                    acc: self.trace[..i].iter().rev().find(|t| t.reg == ACC).map_or(0, |t| t.after),
+                });
+            }
+            first[trace.op] = Some(i);
+        }
+        None
+    }
+}
+
+/// Steps `cont` makes in a script unless given a limit.
+pub const CONT_LIMIT: usize = 1_000_000;
+
+/// Debugger command, as written in a script: `break 4`, `break acc=5`, `watch acc*10`, `step`,
+/// `back`, `cont [limit]`, `trace` or `loop`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Command {
+    Break(Breakpoint),
+    Watch(Expr),
+    Step,
+    Back,
+    Cont(usize),
+    Trace,
+    Loop,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self> {
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+        match (name, arg) {
+            ("break", "") | ("watch", "") => Err(Error::new(line, format!("expected `{} <arg>`", name))),
+            ("break", arg) => match arg.strip_prefix("acc=") {
+                Some(acc) => number(acc).map(|acc| Command::Break(Breakpoint::Acc(acc))),
+                None => number(arg).map(|op| Command::Break(Breakpoint::Op(op))),
+            },
+            ("watch", expr) => Expr::parse(expr).map(Command::Watch),
+            ("cont", "") => Ok(Command::Cont(CONT_LIMIT)),
+            ("cont", limit) => number(limit).map(Command::Cont),
+            ("step", "") => Ok(Command::Step),
+            ("back", "") => Ok(Command::Back),
+            ("trace", "") => Ok(Command::Trace),
+            ("loop", "") => Ok(Command::Loop),
+            ("step" | "back" | "trace" | "loop", _) => Err(Error::new(line, format!("expected `{}` without arguments", name))),
+            _ => Err(Error::new(line, "unknown command")),
+        }
+    }
+}
+
+/// Debugger script: a command per line, with `#` starting a comment.
+pub fn script(text: &str) -> Result<Vec<Command>> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.split('#').next().unwrap().trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| Command::parse(line).map_err(|e| e.at(i + 1)))
         .collect()
 }
 
+impl Debugger {
+    /// Run the command, returns what it reports (empty for `trace` with nothing executed yet).
+    pub fn exec(&mut self, command: &Command) -> String {
+        match command {
+            Command::Break(breakpoint) => {
+                self.add_breakpoint(*breakpoint);
+                format!("breakpoint {}", breakpoint)
+            },
+            Command::Watch(expr) => {
+                let i = self.watch(expr.clone());
+                format!("watch {}: {}", i, self.watches()[i])
+            },
+            Command::Step => if self.step() {
+                self.trace.last().unwrap().to_string()
+            } else {
+                Stop::Terminated.to_string()
+            },
+            Command::Back => if self.back() {
+                format!("back to {} with acc {}", self.run.op(), self.run.acc())
+            } else {
+                "nothing to undo".to_string()
+            },
+            Command::Cont(limit) => {
+                let stop = self.cont(*limit);
+                format!("{} at {} with acc {}", stop, self.run.op(), self.run.acc())
+            },
+            Command::Trace => self.trace.iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Command::Loop => match self.loop_report() {
+                Some(report) => report.to_string().trim_end().to_string(),
+                None => "no loop".to_string(),
+            },
+        }
+    }
+}
+
+/// Loop the code gets stuck in, `None` if it terminates.
+pub fn find_loop(code: &[Op]) -> Option<Loop> {
+    let mut debugger = Debugger::new(code);
+    match debugger.cont(usize::MAX) {
+        Stop::Loop(_) => {
+            debugger.step();
+            debugger.loop_report()
+        },
+        _ => None,
+    }
+}
+
 // Return copy of code with jmp <-> nop swap at given index
+#[allow(dead_code)]
 fn swap(mut code: Vec<Op>, at: usize) -> Vec<Op> {
     fn flip(op: &Op) -> Op {
         match op {
@@ -115,46 +631,239 @@ fn swap(mut code: Vec<Op>, at: usize) -> Vec<Op> {
     code
 }
 
-// Find the jmp <-> nop swap that terminates the program
-fn find(code: Vec<Op>) -> Option<Run> {
-    let swaps = pick(&code);
+// Instruction index after jumping by the offset, if it is within the program or right after it.
+fn target(code: &[Op], at: usize, offset: i64) -> Option<usize> {
+    i64::try_from(at).ok()
+        .and_then(|at| at.checked_add(offset))
+        .and_then(|to| usize::try_from(to).ok())
+        .filter(|to| *to <= code.len())
+}
 
-    for i in swaps {
-        let modified = swap(code.clone(), i);
-        let run = exec(&modified);
-        if run.terminated() {
-            return Some(run);
+// Possible next instructions: both for conditional jumps, none for `halt`.
+fn successors(code: &[Op], at: usize) -> Vec<usize> {
+    match code[at] {
+        Op::Jmp(arg) => target(code, at, arg).into_iter().collect(),
+        Op::Jz(_, arg) | Op::Jnz(_, arg) => target(code, at, 1).into_iter().chain(target(code, at, arg)).collect(),
+        Op::Halt => vec![],
+        _ => target(code, at, 1).into_iter().collect(),
+    }
+}
+
+/// Instructions from which the program (possibly) terminates, with one more entry for the end of
+/// the program: everything reachable from the end and from `halt` over the reversed control flow.
+pub fn terminating(code: &[Op]) -> Vec<bool> {
+    let mut predecessors = vec![Vec::new(); code.len() + 1];
+    for at in 0..code.len() {
+        for next in successors(code, at) {
+            predecessors[next].push(at);
+        }
+    }
+
+    let mut reached = vec![false; code.len() + 1];
+    let mut stack: Vec<usize> = (0..code.len()).filter(|at| code[*at] == Op::Halt).collect();
+    stack.push(code.len());
+    while let Some(at) = stack.pop() {
+        if !reached[at] {
+            reached[at] = true;
+            stack.extend(&predecessors[at]);
+        }
+    }
+    reached
+}
+
+// Accumulator gained on the way from each instruction (and the end of the program) to termination,
+// for those whose way there doesn't depend on the registers: no conditional jumps and only
+// constants added to `acc`. Each such instruction has a single successor, so it's a single pass
+// over the reversed control flow from the end and from `halt`.
+fn gains(code: &[Op]) -> Vec<Option<i64>> {
+    let mut predecessors = vec![Vec::new(); code.len() + 1];
+    for at in 0..code.len() {
+        let gain = match code[at] {
+            Op::Acc(n) | Op::Add(ACC, Arg::Num(n)) => Some(n),
+            Op::Add(r, _) | Op::Mul(r, _) | Op::Set(r, _) => (r != ACC).then_some(0),
+            Op::Jmp(_) | Op::Nop(_) => Some(0),
+            Op::Jz(..) | Op::Jnz(..) | Op::Halt => None,
+        };
+        if let Some(gain) = gain {
+            for next in successors(code, at) {
+                predecessors[next].push((at, gain));
+            }
+        }
+    }
+
+    let mut gains = vec![None; code.len() + 1];
+    let mut stack: Vec<usize> = (0..code.len()).filter(|at| code[*at] == Op::Halt).collect();
+    stack.push(code.len());
+    for at in &stack {
+        gains[*at] = Some(0);
+    }
+    while let Some(at) = stack.pop() {
+        let total: i64 = gains[at].unwrap();
+        for (prev, gain) in &predecessors[at] {
+            gains[*prev] = Some(total.wrapping_add(*gain));
+            stack.push(*prev);
+        }
+    }
+    gains
+}
+
+/// Single `jmp` <-> `nop` flip making the program terminate, with the accumulator at termination.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Fix {
+    pub op: usize,
+    pub acc: i64,
+}
+
+/// Flips that make a looping program terminate, at most `limit` of them: the first ones in the
+/// order the program runs into the flipped instructions. Only instructions executed before looping
+/// can fix the program, and the fixed program runs as the original one up to the flip, so a single
+/// run finds the fixes: the flipped instruction has to lead to a terminating one, and the
+/// accumulator gained from there on is known from `gains`. Only where that depends on the registers
+/// (conditional jumps, `acc` computed from them) the run continues from the flip to find out.
+pub fn repairs(code: &[Op], limit: usize) -> Vec<Fix> {
+    let reached = terminating(code);
+    let gains = gains(code);
+
+    let mut run = Run::new(code);
+    let mut visited = vec![false; code.len()];
+    // Instructions visited after each flip, marked with the index of the flipped one.
+    let mut seen = vec![usize::MAX; code.len()];
+    let mut fixes = Vec::new();
+    while !run.terminated() && !visited[run.op()] {
+        let op = run.op();
+        visited[op] = true;
+        let flipped = match code[op] {
+            Op::Jmp(_) => target(code, op, 1),
+            Op::Nop(arg) => target(code, op, arg),
+            _ => None,
+        };
+        if let Some(next) = flipped.filter(|next| fixes.len() < limit && reached[*next]) {
+            let acc = match gains[next] {
+                Some(gain) => Some(run.acc().wrapping_add(gain)),
+                None => run.resume(next, |at| visited[at] || std::mem::replace(&mut seen[at], op) == op),
+            };
+            fixes.extend(acc.map(|acc| Fix { op, acc }));
         }
+        run.step();
     }
+    if run.terminated() {
+        return vec![];
+    }
+    fixes
+}
 
-    None
+// Jump offset: a number, or a label resolved relative to the instruction at `at`.
+fn offset(arg: &str, at: usize, labels: &HashMap<&str, usize>) -> Result<i64> {
+    if arg.starts_with(|c: char| c == '+' || c == '-' || c.is_ascii_digit()) {
+        number(arg)
+    } else {
+        labels.get(arg)
+            .map(|to| *to as i64 - at as i64)
+            .ok_or_else(|| Error::new(arg, "unknown label"))
+    }
 }
 
-fn parse(op: &str) -> Op {
-    let tokens: Vec<&str> = op.split(" ").collect();
+// Instruction at index `at` of the program.
+fn instruction(op: &str, at: usize, labels: &HashMap<&str, usize>) -> Result<Op> {
+    let reg = |name: &str| register(name).ok_or_else(|| Error::new(name, "unknown register"));
+    let arg = |arg: &str| match register(arg) {
+        Some(r) => Ok(Arg::Reg(r)),
+        None => number(arg).map(Arg::Num),
+    };
 
-    let arg: i64 = tokens[1].parse().unwrap();
-    match tokens[0] {
-        "acc" => Op::Acc(arg),
-        "jmp" => Op::Jmp(arg),
-        "nop" => Op::Nop(arg),
-        _     => unreachable!()
+    let tokens: Vec<&str> = op.split_whitespace().collect();
+    match tokens[..] {
+        ["acc", n] => Ok(Op::Acc(number(n)?)),
+        ["jmp", n] => Ok(Op::Jmp(offset(n, at, labels)?)),
+        ["nop", n] => Ok(Op::Nop(offset(n, at, labels)?)),
+        ["add", r, a] => Ok(Op::Add(reg(r)?, arg(a)?)),
+        ["mul", r, a] => Ok(Op::Mul(reg(r)?, arg(a)?)),
+        ["set", r, a] => Ok(Op::Set(reg(r)?, arg(a)?)),
+        ["jz", r, n] => Ok(Op::Jz(reg(r)?, offset(n, at, labels)?)),
+        ["jnz", r, n] => Ok(Op::Jnz(reg(r)?, offset(n, at, labels)?)),
+        ["halt"] => Ok(Op::Halt),
+        ["acc" | "jmp" | "nop", ..] => Err(Error::new(op, "expected `<op> <arg>`")),
+        ["add" | "mul" | "set" | "jz" | "jnz", ..] => Err(Error::new(op, "expected `<op> <register> <arg>`")),
+        ["halt", ..] => Err(Error::new(op, "expected `halt` without arguments")),
+        _ => Err(Error::new(op, "unknown opcode")),
     }
 }
 
-fn input() -> Vec<Op> {
-    lines().iter()
-        .map(|line| parse(line))
+/// Single instruction, with offsets only.
+pub fn parse(op: &str) -> Result<Op> {
+    instruction(op, 0, &HashMap::new())
+}
+
+/// Assemble the program: an instruction per line, optionally preceded by a `label:`, with `#`
+/// starting a comment. Jumps (including `nop`) take either an offset or a label, and (except for
+/// `nop`) must land within the program or right after it.
+pub fn assemble(text: &str) -> Result<Vec<Op>> {
+    // Instructions with their line numbers, and instruction indices of the labels.
+    let mut ops: Vec<(usize, &str)> = Vec::new();
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut line = line.split('#').next().unwrap().trim();
+        if let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            let valid = label.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') &&
+                label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(Error::new(label, "invalid label").at(i + 1));
+            }
+            if labels.insert(label, ops.len()).is_some() {
+                return Err(Error::new(label, "duplicate label").at(i + 1));
+            }
+            line = rest.trim();
+        }
+        if !line.is_empty() {
+            ops.push((i + 1, line));
+        }
+    }
+
+    if ops.is_empty() {
+        return Err(Error::new("", "empty program"));
+    }
+
+    let code = ops.iter().enumerate()
+        .map(|(at, (line, op))| instruction(op, at, &labels).map_err(|e| e.at(*line)))
+        .collect::<Result<Vec<Op>>>()?;
+    for (at, (line, op)) in ops.iter().enumerate() {
+        let offset = match code[at] {
+            Op::Jmp(arg) | Op::Jz(_, arg) | Op::Jnz(_, arg) => arg,
+            _ => continue,
+        };
+        if target(&code, at, offset).is_none() {
+            return Err(Error::new(op, "jump out of the program").at(*line));
+        }
+    }
+    Ok(code)
+}
+
+/// Canonical text of the program: an instruction per line, with offsets instead of labels.
+pub fn disassemble(code: &[Op]) -> String {
+    code.iter()
+        .map(|op| op.to_string() + "\n")
         .collect()
 }
 
-pub fn main() {
-    let code = input();
-    let run = exec(&code);
-    println!("{}", run.acc());
+pub struct Day08;
+
+impl Solution for Day08 {
+    type Input = Vec<Op>;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        assemble(text)
+    }
 
-    let run = find(code);
-    println!("{}", run.unwrap().acc());
+    fn part1(code: &Self::Input) -> i64 {
+        exec(code).acc()
+    }
+
+    fn part2(code: &Self::Input) -> i64 {
+        repairs(code, 1)[0].acc
+    }
 }
 
 #[cfg(test)]
@@ -168,10 +877,142 @@ mod tests {
             ("nop +0", Nop(0)),
             ("acc +1", Acc(1)),
             ("jmp -4", Jmp(-4)),
+            ("add b +3", Add(2, Arg::Num(3))),
+            ("mul acc a", Mul(ACC, Arg::Reg(1))),
+            ("set d -1", Set(4, Arg::Num(-1))),
+            ("jz c +2", Jz(3, 2)),
+            ("jnz acc -1", Jnz(ACC, -1)),
+            ("halt", Halt),
         ];
 
         for (s, op) in input {
-            assert_eq!(parse(s), op);
+            assert_eq!(parse(s), Ok(op));
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse("hlt +0").unwrap_err().reason(), "unknown opcode");
+        assert_eq!(parse("jmp 1x").unwrap_err().reason(), "invalid number");
+        assert_eq!(parse("jmp x").unwrap_err().reason(), "unknown label");
+        assert_eq!(parse("add e +1").unwrap_err().reason(), "unknown register");
+        assert_eq!(parse("mul +2").unwrap_err().reason(), "expected `<op> <register> <arg>`");
+        assert!(parse("halt +0").is_err());
+        assert!(parse("nop").is_err());
+
+        let e = assemble("nop +0\nacc +1\nmul +2\n").unwrap_err();
+        assert_eq!(e.line(), Some(3));
+        assert_eq!(e.text(), "mul +2");
+    }
+
+    #[test]
+    fn test_assemble() {
+        let text = "\
+            # acc = 5!
+                set a +5
+                set acc 1
+            loop:
+                mul acc a   # acc *= a
+                add a -1
+                jnz a loop
+            end: halt
+                jmp end
+        ";
+        let code = assemble(text).unwrap();
+        assert_eq!(code, vec![
+            Set(1, Arg::Num(5)),
+            Set(ACC, Arg::Num(1)),
+            Mul(ACC, Arg::Reg(1)),
+            Add(1, Arg::Num(-1)),
+            Jnz(1, -2),
+            Halt,
+            Jmp(-1),
+        ]);
+
+        let mut run = Run::new(&code);
+        while !run.terminated() {
+            run.step();
+        }
+        assert_eq!((run.acc(), run.reg(1), run.op()), (120, 0, 5));
+    }
+
+    #[test]
+    fn test_assemble_invalid() {
+        let error = |text: &str| {
+            let e = assemble(text).unwrap_err();
+            (e.line().unwrap(), e.reason().to_string())
+        };
+        assert_eq!(error("nop +0\n\n# comment\njmp out\n"), (4, "unknown label".to_string()));
+        assert_eq!(error("a: nop +0\na: jmp a\n"), (2, "duplicate label".to_string()));
+        assert_eq!(error("acc +1\n1x: jmp +0\n"), (2, "invalid label".to_string()));
+        assert_eq!(error("acc +1\nmov a +1\n"), (2, "unknown opcode".to_string()));
+        assert_eq!(error("jmp -5\n"), (1, "jump out of the program".to_string()));
+        assert_eq!(error("acc +1\njmp +7\n"), (2, "jump out of the program".to_string()));
+        assert_eq!(error("set a +1\n# end\njnz a -3\n"), (3, "jump out of the program".to_string()));
+        assert!(assemble("acc +1\njmp +1\n").is_ok());
+        assert!(assemble("nop -5\n").is_ok());
+
+        let e = assemble("# nothing\n\n").unwrap_err();
+        assert_eq!((e.line(), e.reason()), (None, "empty program"));
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let text = "top: set b +2\nloop: add b -1 # count down\njnz b loop\njz acc top\nnop +0\nhalt\n";
+        let code = assemble(text).unwrap();
+        let canonical = disassemble(&code);
+
+        assert_eq!(canonical, "set b +2\nadd b -1\njnz b -1\njz acc -3\nnop +0\nhalt\n");
+        assert_eq!(assemble(&canonical).unwrap(), code);
+    }
+
+    #[test]
+    fn test_terminating() {
+        let code = assemble(include_str!("../inputs/examples/day08-example.txt")).unwrap();
+        let reached = terminating(&code);
+        let ops = (0..reached.len()).filter(|at| reached[*at]).collect::<Vec<_>>();
+        assert_eq!(ops, vec![8, 9]);
+
+        let code = assemble("jz a +2\nhalt\njmp -1\njmp +0\n").unwrap();
+        assert_eq!(terminating(&code), vec![true, true, true, false, true]);
+    }
+
+    #[test]
+    fn test_repairs() {
+        let code = assemble(include_str!("../inputs/examples/day08-example.txt")).unwrap();
+        assert_eq!(repairs(&code, usize::MAX), vec![Fix { op: 7, acc: 8 }]);
+        assert_eq!(repairs(&code, 1), vec![Fix { op: 7, acc: 8 }]);
+        assert_eq!(repairs(&swap(code, 7), usize::MAX), vec![]);
+
+        let code = assemble("nop +2\njmp +0\nacc +1\n").unwrap();
+        assert_eq!(repairs(&code, usize::MAX), vec![Fix { op: 0, acc: 1 }, Fix { op: 1, acc: 1 }]);
+        assert_eq!(repairs(&code, 1), vec![Fix { op: 0, acc: 1 }]);
+        assert_eq!(repairs(&code, 0), vec![]);
+
+        // Flipping `nop +3` could reach `halt` if the conditional jump wasn't taken, but it always is.
+        let code = assemble("set a +1\nnop +3\njnz a +2\nhalt\njmp -3\n").unwrap();
+        assert!(terminating(&code)[4]);
+        assert_eq!(repairs(&code, usize::MAX), vec![Fix { op: 4, acc: 0 }]);
+
+        // Constant gains after the flip, and gains depending on a register.
+        let programs = [
+            "nop +3\nacc +1\njmp -2\nacc +2\nadd acc +3\n",
+            "set a +5\nnop +2\njmp -1\nadd acc a\n",
+            "set a +0\nnop +2\njmp -1\njz a +2\njmp +0\nadd acc +7\n",
+        ];
+        let expected = [
+            vec![Fix { op: 0, acc: 5 }, Fix { op: 2, acc: 6 }],
+            vec![Fix { op: 1, acc: 5 }, Fix { op: 2, acc: 5 }],
+            vec![Fix { op: 1, acc: 7 }, Fix { op: 2, acc: 7 }],
+        ];
+        for (text, expected) in programs.iter().zip(&expected) {
+            let code = assemble(text).unwrap();
+            let fixes = repairs(&code, usize::MAX);
+            assert_eq!(&fixes, expected, "{}", text);
+            for fix in &fixes {
+                assert_eq!(exec(&swap(code.clone(), fix.op)).acc(), fix.acc);
+            }
+            assert_eq!(repairs(&code, 1), expected[..1].to_vec());
         }
     }
 
@@ -198,5 +1039,162 @@ mod tests {
         run.step();
         assert_eq!(run.op(), 0);
         assert_eq!(run.acc(), 1);
+
+        // Hand-built code can jump out of the program, which stops the run at the jump.
+        let mut run = Run::new(&[Acc(1), Jmp(-5)]);
+        run.step();
+        run.step();
+        assert!(run.terminated() && run.faulted());
+        assert_eq!((run.op(), run.acc()), (1, 1));
+
+        let mut run = Run::new(&[Jz(ACC, i64::MAX)]);
+        run.step();
+        assert!(run.faulted());
+
+        let mut run = Run::new(&[]);
+        assert!(run.terminated() && !run.faulted());
+        run.step();
+        assert_eq!(run.op(), 0);
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Squaring 2 overflows on the sixth `mul` and stays 0 from there on.
+        let code = assemble("set a +2\nmul a a\njmp -1\n").unwrap();
+        let mut run = Run::new(&code);
+        run.step();
+        let mut values = Vec::new();
+        for _ in 0..7 {
+            run.step();
+            values.push(run.reg(1));
+            run.step();
+        }
+        assert_eq!(values, vec![4, 16, 256, 1 << 16, 1 << 32, 0, 0]);
+
+        let mut run = Run::new(&[Acc(i64::MAX), Acc(1), Add(1, Arg::Num(i64::MIN)), Add(1, Arg::Num(-1))]);
+        for _ in 0..4 {
+            run.step();
+        }
+        assert_eq!((run.acc(), run.reg(1)), (i64::MIN, i64::MAX));
+        assert_eq!(Expr::parse("acc - 1").unwrap().eval(&run), i64::MAX);
+        assert_eq!(Expr::parse("a * a").unwrap().eval(&run), 1);
+        assert_eq!(Expr::parse("a + 1").unwrap().eval(&run), i64::MIN);
+
+        let mut debugger = Debugger::new(&code);
+        assert_eq!(debugger.cont(100), Stop::Loop(1));
+    }
+
+    #[test]
+    fn test_expr() {
+        let mut run = Run::new(&[Acc(5), Nop(0)]);
+        run.step();
+
+        assert_eq!(Expr::parse("acc").unwrap().eval(&run), 5);
+        assert_eq!(Expr::parse("acc - 2 * op").unwrap().eval(&run), 3);
+        assert_eq!(Expr::parse("(acc - 2) * -op").unwrap().eval(&run), -3);
+        assert_eq!(Expr::parse("b * 2"), Ok(Expr::Mul(Box::new(Expr::Reg(2)), Box::new(Expr::Num(2)))));
+        assert!(Expr::parse("acc +").is_err());
+        assert!(Expr::parse("(acc").is_err());
+        assert_eq!(Expr::parse("acc / 2").unwrap_err().reason(), "unexpected `/`");
+    }
+
+    #[test]
+    fn test_debugger() {
+        let code = assemble(include_str!("../inputs/examples/day08-example.txt")).unwrap();
+        let mut debugger = Debugger::new(&code);
+
+        debugger.add_breakpoint(Breakpoint::Op(4));
+        debugger.add_breakpoint(Breakpoint::Acc(2));
+        assert_eq!(debugger.cont(100), Stop::Breakpoint(Breakpoint::Acc(2)));
+        assert_eq!(debugger.run().op(), 7);
+        assert_eq!(debugger.cont(100), Stop::Breakpoint(Breakpoint::Op(4)));
+        assert_eq!(debugger.run().acc(), 5);
+        debugger.remove_breakpoint(Breakpoint::Op(4));
+        debugger.remove_breakpoint(Breakpoint::Acc(2));
+
+        let watch = debugger.watch(Expr::parse("acc * 10").unwrap());
+        assert_eq!(debugger.cont(100), Stop::Loop(1));
+        assert_eq!(debugger.cont(100), Stop::Watch(watch, 50, 60));
+        assert_eq!(debugger.watches(), vec![60]);
+        assert_eq!(debugger.cont(1), Stop::Limit);
+        assert_eq!(debugger.trace().iter().map(|t| t.op).collect::<Vec<_>>(), vec![0, 1, 2, 6, 7, 3, 4, 1, 2]);
+        assert_eq!(debugger.trace()[5].to_string(), "   3: acc +3     acc 2 -> 5");
+
+        assert!(debugger.back());
+        assert!(debugger.back());
+        assert_eq!((debugger.run().op(), debugger.run().acc()), (1, 5));
+        while debugger.back() {}
+        assert_eq!((debugger.run().op(), debugger.run().acc()), (0, 0));
+        assert!(debugger.trace().is_empty());
+    }
+
+    #[test]
+    fn test_script() {
+        let commands = script("break 4\n  break acc=5 # set to 5\n\nwatch acc*10\nstep\nback\ncont\ncont 3\ntrace\nloop\n").unwrap();
+        assert_eq!(commands, vec![
+            Command::Break(Breakpoint::Op(4)),
+            Command::Break(Breakpoint::Acc(5)),
+            Command::Watch(Expr::Mul(Box::new(Expr::Reg(ACC)), Box::new(Expr::Num(10)))),
+            Command::Step,
+            Command::Back,
+            Command::Cont(CONT_LIMIT),
+            Command::Cont(3),
+            Command::Trace,
+            Command::Loop,
+        ]);
+
+        let error = |text: &str| {
+            let e = script(text).unwrap_err();
+            (e.line().unwrap(), e.reason().to_string())
+        };
+        assert_eq!(error("step\nrun\n"), (2, "unknown command".to_string()));
+        assert_eq!(error("# start\nbreak\n"), (2, "expected `break <arg>`".to_string()));
+        assert_eq!(error("break acc=x\n"), (1, "invalid number".to_string()));
+        assert_eq!(error("step 2\n"), (1, "expected `step` without arguments".to_string()));
+        assert_eq!(error("watch acc /\n"), (1, "unexpected `/`".to_string()));
+    }
+
+    #[test]
+    fn test_exec() {
+        let code = assemble(include_str!("../inputs/examples/day08-example.txt")).unwrap();
+        let mut debugger = Debugger::new(&code);
+        let commands = script("trace\nbreak 4\nwatch acc*10\nstep\nback\nback\ncont\ncont\ncont\nloop\n").unwrap();
+        let output = commands.iter().map(|c| debugger.exec(c)).collect::<Vec<_>>();
+        assert_eq!(output, vec![
+            "",
+            "breakpoint 4",
+            "watch 0: 0",
+            "   0: nop +0     acc 0 -> 0",
+            "back to 0 with acc 0",
+            "nothing to undo",
+            "watch 0: 0 -> 10 at 2 with acc 1",
+            "watch 0: 10 -> 20 at 7 with acc 2",
+            "watch 0: 20 -> 50 at 4 with acc 5",
+            "no loop",
+        ]);
+
+        let mut debugger = Debugger::new(&code);
+        let output = script("cont\nstep\nloop\ncont 2\n").unwrap().iter().map(|c| debugger.exec(c)).collect::<Vec<_>>();
+        assert_eq!(output[0], "loop back to 1 at 1 with acc 5");
+        assert_eq!(output[1], "   1: acc +1     acc 5 -> 6");
+        assert!(output[2].starts_with("loop of 6 instructions entered after 1 steps with acc 5:\n   1: acc +1\n"));
+        assert_eq!(output[3], "step limit at 7 with acc 7");
+
+        let mut debugger = Debugger::new(&swap(code, 7));
+        assert_eq!(debugger.exec(&Command::Cont(CONT_LIMIT)), "terminated at 9 with acc 8");
+        assert_eq!(debugger.exec(&Command::Step), "terminated");
+    }
+
+    #[test]
+    fn test_loop() {
+        let code = assemble(include_str!("../inputs/examples/day08-example.txt")).unwrap();
+        let report = find_loop(&code).unwrap();
+
+        assert_eq!(report.prefix, vec![0]);
+        assert_eq!(report.cycle.iter().map(|(op, _)| *op).collect::<Vec<_>>(), vec![1, 2, 6, 7, 3, 4]);
+        assert_eq!(report.acc, 5);
+        assert_eq!(report.to_string().lines().next(), Some("loop of 6 instructions entered after 1 steps with acc 5:"));
+
+        assert_eq!(find_loop(&swap(code, 7)), None);
     }
 }
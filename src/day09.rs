@@ -1,68 +1,216 @@
-use crate::utils::lines;
-use crate::day01::find2;
+use std::collections::{HashMap, VecDeque};
+use std::io::BufRead;
+use std::ops::Range;
 
-fn input() -> Vec<i64> {
-    lines().into_iter()
-        .map(|line| line.parse().unwrap())
-        .collect()
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
+
+/// Numbers of the cipher with the length of its preamble, which is 25 unless the first line says
+/// `preamble <length>` (examples from the puzzle text use a shorter one).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cipher {
+    pub preamble: usize,
+    pub numbers: Vec<i64>,
+}
+
+fn input(text: &str) -> Result<Cipher> {
+    let first = text.lines().next().unwrap_or_default();
+    let Some(length) = first.strip_prefix("preamble ") else {
+        return Ok(Cipher { preamble: PREAMBLE, numbers: parse_lines(text.lines(), number)? });
+    };
+    let preamble = number(length).map_err(|e| e.at(1))?;
+    if preamble < 2 {
+        return Err(Error::new(first, "preamble shorter than 2 numbers").at(1));
+    }
+    let numbers = parse_lines(text.lines().skip(1), number).map_err(|e| e.offset(1))?;
+    Ok(Cipher { preamble, numbers })
 }
 
-fn find(numbers: &[i64], k: usize) -> Option<i64> {
-    numbers.windows(k + 1)
-        .find(|window| {
-            let n = *window.last().unwrap();
-            let mut preamble: Vec<i64> = window.iter()
-                .take(k)
-                .map(|x| *x)
-                .collect();
-            preamble.sort();
-            find2(&preamble, n).is_none()
+/// Validator of a stream of numbers: every number after the preamble has to be a sum of two numbers
+/// among the `preamble` numbers right before it. Keeps the count of pairs for each sum in the window,
+/// so each number is checked in constant time and the window moves in time linear in its length.
+pub struct Validator {
+    preamble: usize,
+    window: VecDeque<i64>,
+    sums: HashMap<i64, usize>,
+    position: usize,
+}
+
+impl Validator {
+    pub fn new(preamble: usize) -> Self {
+        assert!(preamble >= 2);
+        Self {
+            preamble,
+            window: VecDeque::with_capacity(preamble),
+            sums: HashMap::new(),
+            position: 0,
+        }
+    }
+
+    /// Position of the next number in the stream.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Take the next number, returns false if it is invalid.
+    pub fn push(&mut self, n: i64) -> bool {
+        let valid = self.window.len() < self.preamble || self.sums.contains_key(&n);
+
+        if self.window.len() == self.preamble {
+            let first = self.window.pop_front().unwrap();
+            for x in &self.window {
+                let count = self.sums.get_mut(&(first + x)).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    self.sums.remove(&(first + x));
+                }
+            }
+        }
+        for x in &self.window {
+            *self.sums.entry(n + x).or_insert(0) += 1;
+        }
+        self.window.push_back(n);
+        self.position += 1;
+        valid
+    }
+}
+
+/// Every invalid number with its position in the stream.
+pub fn invalid<I: IntoIterator<Item = i64>>(numbers: I, preamble: usize) -> Vec<(usize, i64)> {
+    let mut validator = Validator::new(preamble);
+    numbers.into_iter()
+        .filter_map(|n| {
+            let at = validator.position();
+            if validator.push(n) { None } else { Some((at, n)) }
         })
-        .map(|window| *window.last().unwrap())
+        .collect()
 }
 
-// Find a contiguous set of at least two numbers in your list which sum to the target.
-fn find_subsum(numbers: &[i64], sum: i64) -> Option<&[i64]> {
-    // Check if sub-slice starting at index `at` sums up to target sum, and return length if yes.
-    fn check_at(numbers: &[i64], sum: i64, at: usize) -> Option<usize> {
-        let mut remaining = sum;
-        for i in at..numbers.len() {
-            let x = numbers[i];
-            if x < remaining {
-                remaining -= x;
-            } else if x == remaining {
-                return Some(i - at + 1);
-            } else if x > remaining {
-                return None;
+/// Same as `invalid`, but reading a number per line.
+pub fn validate<R: BufRead>(reader: R, preamble: usize) -> Result<Vec<(usize, i64)>> {
+    let mut validator = Validator::new(preamble);
+    let mut found = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| Error::new("", format!("failed to read: {}", e)).at(i + 1))?;
+        let n = number(line.trim_end()).map_err(|e| e.at(i + 1))?;
+        if !validator.push(n) {
+            found.push((i, n));
+        }
+    }
+    Ok(found)
+}
+
+fn find(numbers: &[i64], preamble: usize) -> Option<i64> {
+    let mut validator = Validator::new(preamble);
+    numbers.iter().copied().find(|n| !validator.push(*n))
+}
+
+/// Every contiguous range of at least `min_len` (and at least one) numbers adding up to the target,
+/// ordered by start and then end. Positions of prefix sums are looked up by value, so it works with
+/// negative numbers too, in time linear in the number of numbers and ranges found.
+pub fn ranges(numbers: &[i64], target: i64, min_len: usize) -> Vec<Range<usize>> {
+    let min_len = min_len.max(1);
+    let prefix = prefix_sums(numbers);
+
+    // Positions of each prefix sum in increasing order, each with a cursor past the positions
+    // too close to the current start; the cursors only move forward.
+    let mut ends: HashMap<i64, (Vec<usize>, usize)> = HashMap::new();
+    for (end, sum) in prefix.iter().enumerate() {
+        ends.entry(*sum).or_default().0.push(end);
+    }
+    let mut found = Vec::new();
+    for (start, sum) in prefix.iter().enumerate() {
+        if let Some((positions, skip)) = ends.get_mut(&(sum + target)) {
+            while *skip < positions.len() && positions[*skip] < start + min_len {
+                *skip += 1;
             }
+            found.extend(positions[*skip..].iter().map(|end| start..*end));
         }
-        None
     }
+    found
+}
+
+// Sums of the first 0, 1, ..., n numbers.
+fn prefix_sums(numbers: &[i64]) -> Vec<i64> {
+    let mut prefix = Vec::with_capacity(numbers.len() + 1);
+    prefix.push(0);
+    for n in numbers {
+        prefix.push(prefix.last().unwrap() + n);
+    }
+    prefix
+}
+
+/// Minimum, maximum and sum of any non-empty range of the numbers in constant time: min and max come
+/// from a sparse table of ranges with power of two lengths (two overlapping ones cover any range),
+/// sums from prefix sums.
+pub struct RangeTable {
+    prefix: Vec<i64>,
+    // `min[k][i]` is the minimum of numbers `i..i + 2^k`, same for `max`.
+    min: Vec<Vec<i64>>,
+    max: Vec<Vec<i64>>,
+}
 
-    for at in 0..numbers.len()-1 {
-        if let Some(n) = check_at(numbers, sum, at) {
-            return Some(&numbers[at..(at + n)]);
+impl RangeTable {
+    pub fn new(numbers: &[i64]) -> Self {
+        let mut min = vec![numbers.to_vec()];
+        let mut max = vec![numbers.to_vec()];
+        let mut len = 1;
+        while 2 * len <= numbers.len() {
+            let level = |table: &[i64], f: fn(i64, i64) -> i64| (0..table.len() - len)
+                .map(|i| f(table[i], table[i + len]))
+                .collect::<Vec<_>>();
+            min.push(level(min.last().unwrap(), i64::min));
+            max.push(level(max.last().unwrap(), i64::max));
+            len *= 2;
         }
+        Self { prefix: prefix_sums(numbers), min, max }
+    }
+
+    // Level of the sparse table and the two (overlapping) ranges covering the range.
+    fn cover(&self, range: &Range<usize>) -> (usize, usize, usize) {
+        assert!(range.start < range.end && range.end < self.prefix.len(), "invalid range {:?}", range);
+        let k = (usize::BITS - 1 - (range.end - range.start).leading_zeros()) as usize;
+        (k, range.start, range.end - (1 << k))
+    }
+
+    pub fn min(&self, range: Range<usize>) -> i64 {
+        let (k, a, b) = self.cover(&range);
+        self.min[k][a].min(self.min[k][b])
+    }
+
+    pub fn max(&self, range: Range<usize>) -> i64 {
+        let (k, a, b) = self.cover(&range);
+        self.max[k][a].max(self.max[k][b])
     }
 
-    None
+    pub fn sum(&self, range: Range<usize>) -> i64 {
+        self.prefix[range.end] - self.prefix[range.start]
+    }
 }
 
-pub fn main() {
-    const K: usize = 25;
+const PREAMBLE: usize = 25;
 
-    let input = input();
-    let found = find(&input, K).unwrap();
-    println!("{}", found);
+pub struct Day09;
 
-    let sum = find_subsum(&input, found)
-        .map(|slice| {
-            let min = *slice.iter().min().unwrap();
-            let max = *slice.iter().max().unwrap();
-            min + max
-        })
-        .unwrap();
-    println!("{}", sum);
+impl Solution for Day09 {
+    type Input = Cipher;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
+
+    fn part1(cipher: &Self::Input) -> i64 {
+        find(&cipher.numbers, cipher.preamble).unwrap()
+    }
+
+    fn part2(cipher: &Self::Input) -> i64 {
+        let found = find(&cipher.numbers, cipher.preamble).unwrap();
+        let range = ranges(&cipher.numbers, found, 2).remove(0);
+        let table = RangeTable::new(&cipher.numbers);
+        table.min(range.clone()) + table.max(range)
+    }
 }
 
 #[cfg(test)]
@@ -79,10 +227,85 @@ mod tests {
     }
 
     #[test]
-    fn test_subsum() {
+    fn test_input() {
+        assert_eq!(input("1\n2\n"), Ok(Cipher { preamble: 25, numbers: vec![1, 2] }));
+        assert_eq!(input("preamble 5\n1\n2\n"), Ok(Cipher { preamble: 5, numbers: vec![1, 2] }));
+        assert_eq!(input(""), Ok(Cipher { preamble: 25, numbers: vec![] }));
+
+        let e = input("preamble 5\n1\nx\n").unwrap_err();
+        assert_eq!((e.line(), e.text(), e.reason()), (Some(3), "x", "invalid number"));
+        let e = input("preamble five\n1\n").unwrap_err();
+        assert_eq!((e.line(), e.text()), (Some(1), "five"));
+        let e = input("preamble 1\n1\n").unwrap_err();
+        assert_eq!((e.line(), e.reason()), (Some(1), "preamble shorter than 2 numbers"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let input = vec![1, 2, 3, 5, 4, 9, 1, 20, 10, 2, 7];
+        assert_eq!(invalid(input.clone(), 3), vec![(4, 4), (6, 1), (7, 20), (9, 2), (10, 7)]);
+        assert_eq!(invalid(input.clone(), 10), vec![]);
+
+        for preamble in 2..input.len() {
+            let expected = (preamble..input.len())
+                .filter(|i| {
+                    let window = &input[i - preamble..*i];
+                    !(0..preamble).any(|a| (a + 1..preamble).any(|b| window[a] + window[b] == input[*i]))
+                })
+                .map(|i| (i, input[i]))
+                .collect::<Vec<_>>();
+            assert_eq!(invalid(input.clone(), preamble), expected);
+        }
+        // Pairs are of different positions, the same number twice is fine.
+        assert_eq!(invalid(vec![3, 3, 6, 12, 9], 2), vec![(3, 12), (4, 9)]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let text = "35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576\n";
+        assert_eq!(validate(text.as_bytes(), 5), Ok(vec![(14, 127)]));
+        let numbers = input(text).unwrap().numbers;
+        assert_eq!(validate(text.as_bytes(), 2), Ok(invalid(numbers, 2)));
+
+        let e = validate("1\n2\nx\n".as_bytes(), 2).unwrap_err();
+        assert_eq!((e.line(), e.text()), (Some(3), "x"));
+    }
+
+    #[test]
+    fn test_ranges() {
         let input: Vec<i64> = vec![35,20,
                                    15,25,47,40, // sums up to 127
                                    62,55,65,95,102,117,150,182,127,219,299,277,309,576];
-        assert_eq!(find_subsum(&input, 127), Some(&input[2..6]));
+        assert_eq!(ranges(&input, 127, 2), vec![2..6]);
+        assert_eq!(ranges(&input, 127, 1), vec![2..6, 14..15]);
+        assert!(ranges(&input, 127, 5).is_empty());
+
+        let input = vec![2, -1, 1, 3, -3, 0, 2];
+        assert_eq!(ranges(&input, 2, 1), vec![0..1, 0..3, 0..5, 0..6, 1..7, 3..7, 5..7, 6..7]);
+        assert_eq!(ranges(&input, 0, 0), ranges(&input, 0, 1));
+        for target in -3..=5 {
+            for min_len in 1..=input.len() {
+                let expected = (0..input.len())
+                    .flat_map(|start| (start + min_len..=input.len()).map(move |end| start..end))
+                    .filter(|range| input[range.clone()].iter().sum::<i64>() == target)
+                    .collect::<Vec<_>>();
+                assert_eq!(ranges(&input, target, min_len), expected);
+            }
+        }
+        assert!(ranges(&[], 0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_range_table() {
+        let input = vec![5, -2, 7, 7, 0, -9, 3, 1, 4, 4, -1];
+        let table = RangeTable::new(&input);
+        for start in 0..input.len() {
+            for end in start + 1..=input.len() {
+                let slice = &input[start..end];
+                assert_eq!(table.min(start..end), *slice.iter().min().unwrap());
+                assert_eq!(table.max(start..end), *slice.iter().max().unwrap());
+                assert_eq!(table.sum(start..end), slice.iter().sum::<i64>());
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,93 +1,234 @@
-use crate::utils::lines;
-
-fn input() -> Vec<u64> {
-    let mut numbers: Vec<u64> = lines().into_iter()
-        .map(|line| line.parse().unwrap())
-        .collect();
-    numbers.sort();
-    numbers
+use std::collections::BTreeMap;
+
+use crate::bignum::BigUint;
+use crate::error::{number, parse_lines, Result};
+use crate::solution::Solution;
+
+fn input(text: &str) -> Result<Vec<u64>> {
+    parse_lines(text.lines(), number)
 }
 
-fn collect(values: &[u64], mut init: u64) -> (u64, u64, u64) {
-    let mut acc: [u64; 3] = [0; 3];
+/// Joltage steps an adapter takes: 1, 2 or 3 jolts lower than its rating.
+pub const STEPS: [u64; 3] = [1, 2, 3];
+
+/// Adapters between the outlet (0 jolts) and the device, with the joltage steps allowed between
+/// two adapters in a chain. The built-in adapter of the device is rated the largest step higher
+/// than the highest adapter, 3 jolts for the puzzle.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    // Outlet, sorted adapters and the device.
+    joltages: Vec<u64>,
+    steps: Vec<u64>,
+    // Number of arrangements from each joltage to the device.
+    ways: Vec<BigUint>,
+}
 
-    for i in 0..values.len() {
-        let x = values[i];
-        let d = x - init;
-        acc[(d as usize) - 1] += 1;
-        init = values[i];
+impl Chain {
+    pub fn new(adapters: &[u64], steps: &[u64]) -> Self {
+        let mut joltages = adapters.to_vec();
+        joltages.sort();
+        let mut steps = steps.to_vec();
+        steps.sort();
+        steps.dedup();
+        joltages.insert(0, 0);
+        joltages.push(joltages.last().unwrap() + steps.last().copied().unwrap_or(0));
+
+        let mut chain = Self { joltages, steps, ways: Vec::new() };
+        let n = chain.joltages.len();
+        let mut ways = vec![BigUint::zero(); n];
+        ways[n - 1] = BigUint::from(1);
+        for i in (0..n - 1).rev() {
+            ways[i] = chain.next(i).fold(BigUint::zero(), |acc, j| acc + &ways[j]);
+        }
+        chain.ways = ways;
+        chain
     }
 
-    acc[2] += 1; // built-it adapter has +3 difference
-    (acc[0], acc[1], acc[2])
-}
+    // Joltages that can follow the one at `i`.
+    fn next(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        let from = self.joltages[i];
+        let max = from + self.steps.last().copied().unwrap_or(0);
+        (i + 1..self.joltages.len())
+            .take_while(move |j| self.joltages[*j] <= max)
+            .filter(move |j| self.steps.contains(&(self.joltages[*j] - from)))
+    }
 
-fn count(init: u64, at: usize, values: &[u64], cache: &mut [u64]) -> u64 {
-    if at >= values.len()-1 {
-        return 1;
+    /// Number of each difference between joltages in the chain of all the adapters.
+    pub fn differences(&self) -> BTreeMap<u64, usize> {
+        let mut differences = BTreeMap::new();
+        for w in self.joltages.windows(2) {
+            *differences.entry(w[1] - w[0]).or_insert(0) += 1;
+        }
+        differences
     }
 
-    if cache[at] > 0 {
-        return cache[at];
+    /// Consecutive joltages with a difference that is not an allowed step, so that the adapters
+    /// can't be all chained together.
+    pub fn gaps(&self) -> Vec<(u64, u64)> {
+        self.joltages.windows(2)
+            .filter(|w| !self.steps.contains(&(w[1] - w[0])))
+            .map(|w| (w[0], w[1]))
+            .collect()
     }
 
-    let mut acc: u64 = 0;
-    let mut i = at;
-    while i < values.len() && values[i] - init <= 3 {
-        acc += count(values[i], i+1, values, cache);
-        i += 1;
+    /// Number of distinct arrangements of adapters connecting the outlet to the device.
+    pub fn arrangements(&self) -> &BigUint {
+        &self.ways[0]
     }
 
-    cache[at] = acc;
-    acc
+    // Adapters of the arrangement given by indices of joltages, without the outlet and the device.
+    fn adapters(&self, path: &[usize]) -> Vec<u64> {
+        path[1..path.len() - 1].iter().map(|i| self.joltages[*i]).collect()
+    }
+
+    /// First `limit` arrangements, as adapter joltages in the order of sorted adapters.
+    pub fn enumerate(&self, limit: usize) -> Vec<Vec<u64>> {
+        let last = self.joltages.len() - 1;
+        let mut found = Vec::new();
+        // Path so far with the remaining options for the next joltage at each step.
+        let mut path = vec![0];
+        let mut options = vec![self.next(0).filter(|j| !self.ways[*j].is_zero()).collect::<Vec<_>>()];
+        while found.len() < limit {
+            let Some(top) = options.last_mut() else { break };
+            if top.is_empty() {
+                options.pop();
+                path.pop();
+                continue;
+            }
+            let j = top.remove(0);
+            path.push(j);
+            if j == last {
+                found.push(self.adapters(&path));
+                path.pop();
+            } else {
+                options.push(self.next(j).filter(|k| !self.ways[*k].is_zero()).collect());
+            }
+        }
+        found
+    }
+
+    /// Uniformly random arrangement, taking random bits from `next`.
+    pub fn sample<F: FnMut() -> u64>(&self, next: F) -> Option<Vec<u64>> {
+        if self.arrangements().is_zero() {
+            return None;
+        }
+        // Index of the arrangement to pick, in the order of `enumerate`.
+        let mut index = self.arrangements().random_below(next);
+        let mut path = vec![0];
+        while *path.last().unwrap() != self.joltages.len() - 1 {
+            for j in self.next(*path.last().unwrap()) {
+                if index < self.ways[j] {
+                    path.push(j);
+                    break;
+                }
+                index -= &self.ways[j];
+            }
+        }
+        Some(self.adapters(&path))
+    }
 }
 
-pub fn main() {
-    let input = input();
+pub struct Day10;
 
-    let (a, _, b) = collect(&input, 0);
-    println!("{}", a * b);
+impl Solution for Day10 {
+    type Input = Chain;
+    type Part1 = usize;
+    type Part2 = BigUint;
 
-    let n = count(0, 0, &input, &mut vec![0; input.len()]);
-    println!("{}", n);
+    fn parse(text: &str) -> Result<Self::Input> {
+        Ok(Chain::new(&input(text)?, &STEPS))
+    }
+
+    fn part1(chain: &Self::Input) -> usize {
+        let differences = chain.differences();
+        differences.get(&1).unwrap_or(&0) * differences.get(&3).unwrap_or(&0)
+    }
+
+    fn part2(chain: &Self::Input) -> BigUint {
+        chain.arrangements().clone()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_collect_small() {
-        let mut numbers = vec![16,10,15,5,1,11,7,19,6,12,4];
-        numbers.sort();
-        assert_eq!(collect(&numbers, 0), (7, 0, 5));
+    fn small() -> Vec<u64> {
+        vec![16,10,15,5,1,11,7,19,6,12,4]
     }
-    
-    #[test]
-    fn test_collect_large() {
-        let mut numbers = vec![28,33,18,42,31,14,46,20,48,47,24,23,49,45,19,38,39,11,1,32,25,35,8,17,7,9,4,2,34,10,3];
-        numbers.sort();
-        assert_eq!(collect(&numbers, 0), (22, 0, 10));
+
+    fn large() -> Vec<u64> {
+        vec![28,33,18,42,31,14,46,20,48,47,24,23,49,45,19,38,39,11,1,32,25,35,8,17,7,9,4,2,34,10,3]
     }
 
     #[test]
-    fn test_count_tiny() {
-        let numbers = vec![1, 2];
-        assert_eq!(count(0, 0, &numbers, &mut vec![0; numbers.len()]), 2);
+    fn test_differences() {
+        let chain = Chain::new(&small(), &STEPS);
+        assert_eq!(chain.differences().into_iter().collect::<Vec<_>>(), vec![(1, 7), (3, 5)]);
+        assert!(chain.gaps().is_empty());
+
+        let chain = Chain::new(&large(), &STEPS);
+        assert_eq!(chain.differences().into_iter().collect::<Vec<_>>(), vec![(1, 22), (3, 10)]);
+
+        let chain = Chain::new(&[1, 3, 3, 8, 9], &STEPS);
+        assert_eq!(chain.differences().into_iter().collect::<Vec<_>>(), vec![(0, 1), (1, 2), (2, 1), (3, 1), (5, 1)]);
+        assert_eq!(chain.gaps(), vec![(3, 3), (3, 8)]);
+        assert!(Chain::new(&[2, 4], &[2]).gaps().is_empty());
+        assert_eq!(Chain::new(&[2, 4], &[2]).differences().into_iter().collect::<Vec<_>>(), vec![(2, 3)]);
+        assert_eq!(Chain::new(&[2, 5], &[2]).gaps(), vec![(2, 5)]);
     }
 
     #[test]
-    fn test_count_small() {
-        let mut numbers = vec![16,10,15,5,1,11,7,19,6,12,4];
-        numbers.sort();
-        assert_eq!(count(0, 0, &numbers, &mut vec![0; numbers.len()]), 8);
+    fn test_arrangements() {
+        assert_eq!(Chain::new(&[1, 2], &STEPS).arrangements().to_u64(), Some(2));
+        assert_eq!(Chain::new(&small(), &STEPS).arrangements().to_u64(), Some(8));
+        assert_eq!(Chain::new(&large(), &STEPS).arrangements().to_u64(), Some(19208));
+
+        // Impossible chains have no arrangements, rather than a cached 0 counted again.
+        assert!(Chain::new(&[1, 5, 6], &STEPS).arrangements().is_zero());
+        assert_eq!(Chain::new(&[1, 2], &[1, 2]).arrangements().to_u64(), Some(2));
+        assert_eq!(Chain::new(&[2, 4, 6], &[2]).arrangements().to_u64(), Some(1));
+        assert!(Chain::new(&[2, 3], &[2]).arrangements().is_zero());
+        // Both of the same adapters can be used, but not together.
+        assert_eq!(Chain::new(&[1, 1], &STEPS).arrangements().to_u64(), Some(2));
+        // The device is 6 jolts above the highest adapter, out of reach from 6.
+        assert_eq!(Chain::new(&[3, 6, 9], &[3, 6]).arrangements().to_u64(), Some(3));
+
+        // Every adapter of 1..=100 chained with steps 1-3 has tribonacci many arrangements.
+        let adapters = (1..=100).collect::<Vec<u64>>();
+        let chain = Chain::new(&adapters, &STEPS);
+        assert_eq!(chain.arrangements().to_string(), "180396380815100901214157639");
     }
 
     #[test]
-    fn test_count_large() {
-        let mut numbers = vec![28,33,18,42,31,14,46,20,48,47,24,23,49,45,19,38,39,11,1,32,25,35,8,17,7,9,4,2,34,10,3];
-        numbers.sort();
-        assert_eq!(count(0, 0, &numbers, &mut vec![0; numbers.len()]), 19208);
+    fn test_enumerate() {
+        let chain = Chain::new(&small(), &STEPS);
+        let all = chain.enumerate(usize::MAX);
+        assert_eq!(all.len(), 8);
+        assert_eq!(all[0], vec![1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19]);
+        assert_eq!(all[7], vec![1, 4, 7, 10, 12, 15, 16, 19]);
+        assert_eq!(chain.enumerate(3), all[..3].to_vec());
+        assert!(Chain::new(&[1, 5], &STEPS).enumerate(10).is_empty());
     }
 
+    #[test]
+    fn test_sample() {
+        let chain = Chain::new(&small(), &STEPS);
+        let all = chain.enumerate(usize::MAX);
+
+        let mut state = 7u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut seen = vec![false; all.len()];
+        for _ in 0..200 {
+            let sample = chain.sample(&mut next).unwrap();
+            seen[all.iter().position(|a| *a == sample).unwrap()] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+        assert_eq!(Chain::new(&[1, 5], &STEPS).sample(&mut next), None);
+    }
 }
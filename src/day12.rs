@@ -1,7 +1,10 @@
-use crate::utils::lines;
+#![allow(clippy::useless_vec)]
+
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
 
 #[derive(Debug, Eq, PartialEq)]
-enum Dir {
+pub enum Dir {
     N, S, W, E
 }
 
@@ -30,14 +33,14 @@ impl Dir {
 }
 
 #[derive(Debug, Eq, PartialEq)]
-enum Rot {
+pub enum Rot {
     L, R
 }
 
 // X: west > 0, east < 0
 // Y: north > 0, south < 0
 #[derive(Debug, Eq, PartialEq)]
-enum Op {
+pub enum Op {
     Move(Dir, i64),
     Turn(Rot, i64),
     Fwd(i64),
@@ -131,47 +134,58 @@ impl Default for Vehicle {
     }
 }
 
-fn parse_op(line: &str) -> Op {
+fn parse_op(line: &str) -> Result<Op> {
     let mut chars = line.chars();
-    let chr = chars.next().unwrap();
-    let num: i64 = chars.as_str().parse().unwrap();
+    let chr = chars.next()
+        .ok_or_else(|| Error::new(line, "empty instruction"))?;
+    let num: i64 = number(chars.as_str())?;
 
     match chr {
-        'N' => Op::Move(Dir::N, num),
-        'S' => Op::Move(Dir::S, num),
-        'W' => Op::Move(Dir::W, num),
-        'E' => Op::Move(Dir::E, num),
-        'L' => Op::Turn(Rot::L, num),
-        'R' => Op::Turn(Rot::R, num),
-        'F' => Op::Fwd(num),
-        _ => unreachable!()
+        'N' => Ok(Op::Move(Dir::N, num)),
+        'S' => Ok(Op::Move(Dir::S, num)),
+        'W' => Ok(Op::Move(Dir::W, num)),
+        'E' => Ok(Op::Move(Dir::E, num)),
+        'L' | 'R' if num != 90 && num != 180 && num != 270 =>
+            Err(Error::new(line, "turn must be 90, 180 or 270 degrees")),
+        'L' => Ok(Op::Turn(Rot::L, num)),
+        'R' => Ok(Op::Turn(Rot::R, num)),
+        'F' => Ok(Op::Fwd(num)),
+        _ => Err(Error::new(line, "unknown action"))
     }
 }
 
-fn input() -> Vec<Op> {
-    lines().iter()
-        .map(|line| parse_op(line))
-        .collect()
+fn input(text: &str) -> Result<Vec<Op>> {
+    parse_lines(text.lines(), parse_op)
 }
 
-pub fn main() {
-    let ops = input();
-
-    let d1 = ops.iter()
-        .fold(Vehicle::default(), |mut v, op| {
-            v.handle1(op);
-            v
-        })
-        .dist();
-    println!("{}", d1);
-
-    let d2 = ops.iter()
-        .fold(Vehicle::default(), |mut v, op| {
-            v.handle2(op);
-            v
-        })
-        .dist();
-    println!("{}", d2);
+pub struct Day12;
+
+impl Solution for Day12 {
+    type Input = Vec<Op>;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
+
+    fn part1(ops: &Self::Input) -> i64 {
+        ops.iter()
+            .fold(Vehicle::default(), |mut v, op| {
+                v.handle1(op);
+                v
+            })
+            .dist()
+    }
+
+    fn part2(ops: &Self::Input) -> i64 {
+        ops.iter()
+            .fold(Vehicle::default(), |mut v, op| {
+                v.handle2(op);
+                v
+            })
+            .dist()
+    }
 }
 
 #[cfg(test)]
@@ -187,14 +201,22 @@ mod tests {
             ("W44", Op::Move(Dir::W, 44)),
             ("E45", Op::Move(Dir::E, 45)),
             ("L90", Op::Turn(Rot::L, 90)),
-            ("R30", Op::Turn(Rot::R, 30)),
+            ("R270", Op::Turn(Rot::R, 270)),
         ];
 
         for (s, op) in cases {
-            assert_eq!(parse_op(s), op);
+            assert_eq!(parse_op(s), Ok(op));
         }
     }
 
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse_op("R30").unwrap_err().reason(), "turn must be 90, 180 or 270 degrees");
+        assert_eq!(parse_op("X10").unwrap_err().reason(), "unknown action");
+        assert_eq!(parse_op("F").unwrap_err().reason(), "invalid number");
+        assert!(parse_op("").is_err());
+    }
+
     #[test]
     fn test_handle1() {
         let moves: Vec<(Op, Pos)> = vec![
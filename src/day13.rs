@@ -1,16 +1,32 @@
-use crate::utils::lines;
-
-fn input() -> (u64, Vec<(u64, u64)>) {
-    let lines = lines();
-    let mut it = lines.iter();
-    let estimate: u64 = it.next().unwrap().parse().unwrap();
-    let timetable = it.next().unwrap()
-        .split(",").into_iter().enumerate()
+#![allow(clippy::ptr_arg)]
+
+use crate::error::{number, Error, Result};
+use crate::solution::Solution;
+
+fn bus(text: &str) -> Result<u64> {
+    match number(text)? {
+        0 => Err(Error::new(text, "bus id must be positive")),
+        id => Ok(id),
+    }
+}
+
+fn input(text: &str) -> Result<(u64, Vec<(u64, u64)>)> {
+    let mut it = text.lines();
+    let estimate: u64 = number(it.next().unwrap_or_default())
+        .map_err(|e| e.at(1))?;
+    let line = it.next()
+        .ok_or_else(|| Error::new("", "missing timetable").at(2))?;
+    let timetable: Vec<(u64, u64)> = line
+        .split(',').enumerate()
         .filter(|(_, x)| *x != "x")
-        .map(|(i, t)| (i as u64, t.parse().unwrap()))
-        .collect();
+        .map(|(i, t)| bus(t).map(|id| (i as u64, id)))
+        .collect::<Result<_>>()
+        .map_err(|e| e.at(2))?;
+    if timetable.is_empty() {
+        return Err(Error::new(line, "no buses in service").at(2));
+    }
 
-    (estimate, timetable)
+    Ok((estimate, timetable))
 }
 
 fn next(since: u64, period: u64) -> u64 {
@@ -47,21 +63,42 @@ fn find_pair(since: u64, one: u64, two: u64, d: u64) -> u64 {
     }
 }
 
-pub fn main() {
-    let (est, table) = input();
+pub struct Day13;
 
-    let ids: Vec<u64> = table.iter().map(|(_, x)| *x).collect();
-    let (w, id) = find_closest_departure(est, &ids);
-    println!("{}", w * id);
+impl Solution for Day13 {
+    type Input = (u64, Vec<(u64, u64)>);
+    type Part1 = u64;
+    type Part2 = u64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
 
-    let t = find_pattern(&table);
-    println!("{}", t);
+    fn part1((est, table): &Self::Input) -> u64 {
+        let ids: Vec<u64> = table.iter().map(|(_, x)| *x).collect();
+        let (w, id) = find_closest_departure(*est, &ids);
+        w * id
+    }
+
+    fn part2((_, table): &Self::Input) -> u64 {
+        find_pattern(table)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_input() {
+        assert_eq!(input("939\n7,13,x,x,59\n"), Ok((939, vec![(0, 7), (1, 13), (4, 59)])));
+
+        assert_eq!(input("939\n").unwrap_err().line(), Some(2));
+        assert_eq!(input("939\nx,x\n").unwrap_err().reason(), "no buses in service");
+        assert_eq!(input("939\n7,0\n").unwrap_err().text(), "0");
+        assert_eq!(input("soon\n7\n").unwrap_err().line(), Some(1));
+    }
+
     #[test]
     fn test_find_closest_departure() {
         let est = 939;
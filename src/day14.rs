@@ -1,8 +1,11 @@
-use crate::utils::lines;
+#![allow(clippy::explicit_auto_deref, clippy::useless_vec)]
+
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
 use std::collections::HashMap;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-enum Op {
+pub enum Op {
     Mask {
         zer: u64,
         one: u64,
@@ -14,25 +17,27 @@ enum Op {
     },
 }
 
-fn parse(line: &str) -> Op {
-    if line.starts_with("mem[") {
-        let mut it = line[4..].split("] = ");
-        let adr: u64 = it.next().unwrap().parse().unwrap();
-        let val: u64 = it.next().unwrap().parse().unwrap();
-        Op::Mem { adr, val }
-    } else { // "mask = "
-        let masked = &line[7..];
-        let one = u64::from_str_radix(&masked.replace("X", "0"), 2).unwrap();
-        let zer = u64::from_str_radix(&masked.replace("X", "1"), 2).unwrap();
-        Op::Mask { zer, one, line: masked.to_string() }
+fn parse(line: &str) -> Result<Op> {
+    if let Some(rest) = line.strip_prefix("mem[") {
+        let (adr, val) = rest.split_once("] = ")
+            .ok_or_else(|| Error::new(line, "expected `mem[adr] = val`"))?;
+        let adr: u64 = number(adr)?;
+        let val: u64 = number(val)?;
+        Ok(Op::Mem { adr, val })
+    } else if let Some(masked) = line.strip_prefix("mask = ") {
+        if masked.len() != 36 || masked.chars().any(|c| c != '0' && c != '1' && c != 'X') {
+            return Err(Error::new(line, "expected 36 bits of `0`, `1` or `X`"));
+        }
+        let one = u64::from_str_radix(&masked.replace('X', "0"), 2).unwrap();
+        let zer = u64::from_str_radix(&masked.replace('X', "1"), 2).unwrap();
+        Ok(Op::Mask { zer, one, line: masked.to_string() })
+    } else {
+        Err(Error::new(line, "expected `mask = ...` or `mem[adr] = val`"))
     }
 }
 
-fn input() -> Vec<Op> {
-    lines()
-        .iter()
-        .map(|line| parse(line))
-        .collect()
+fn input(text: &str) -> Result<Vec<Op>> {
+    parse_lines(text.lines(), parse)
 }
 
 fn mask1(mask: &Op, adr: u64, val: u64) -> Vec<(u64, u64)> {
@@ -108,16 +113,26 @@ fn exec(ops: &Vec<Op>, f: fn(&Op, u64, u64) -> Vec<(u64, u64)>) -> HashMap<u64,
     mem
 }
 
-pub fn main() {
-    let ops = input();
+pub struct Day14;
+
+impl Solution for Day14 {
+    type Input = Vec<Op>;
+    type Part1 = u64;
+    type Part2 = u64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
 
-    let map = exec(&ops, mask1);
-    let sum = map.iter().fold(0u64, |acc, (_, v)| acc + *v);
-    println!("{}", sum);
+    fn part1(ops: &Self::Input) -> u64 {
+        let map = exec(ops, mask1);
+        map.iter().fold(0u64, |acc, (_, v)| acc + *v)
+    }
 
-    let map = exec(&ops, mask2);
-    let sum = map.iter().fold(0u64, |acc, (_, v)| acc + *v);
-    println!("{}", sum);
+    fn part2(ops: &Self::Input) -> u64 {
+        let map = exec(ops, mask2);
+        map.iter().fold(0u64, |acc, (_, v)| acc + *v)
+    }
 }
 
 #[cfg(test)]
@@ -126,7 +141,7 @@ mod tests {
 
     #[test]
     fn test_parse_mem() {
-        let op = parse("mem[2953] = 12201");
+        let op = parse("mem[2953] = 12201").unwrap();
         assert_eq!(op, Op::Mem {
             adr: 2953,
             val: 12201,
@@ -135,7 +150,7 @@ mod tests {
 
     #[test]
     fn test_parse_mask1() {
-        let op = parse("mask = 1000000011XX10X00X0010X1011X111X0111");
+        let op = parse("mask = 1000000011XX10X00X0010X1011X111X0111").unwrap();
         assert_eq!(op, Op::Mask {
             one: 0b100000001100100000001001011011100111u64,
             zer: 0b100000001111101001001011011111110111u64,
@@ -145,7 +160,7 @@ mod tests {
 
     #[test]
     fn test_parse_mask2() {
-        let op = parse("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X");
+        let op = parse("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X").unwrap();
         assert_eq!(op, Op::Mask {
             //     XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X
             zer: 0b111111111111111111111111111111111101u64,
@@ -154,9 +169,18 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("mem[x] = 1").is_err());
+        assert!(parse("mem[1] 1").is_err());
+        assert!(parse("mask = 10X").is_err());
+        assert!(parse("mask = 1000000011XX10X00X0010X1011X111X01Y1").is_err());
+        assert!(parse("nop").is_err());
+    }
+
     #[test]
     fn test_mask1() {
-        let m = parse("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X");
+        let m = parse("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X").unwrap();
 
         let cases: Vec<(&str, Vec<(u64, u64)>)> = vec![
             ("mem[8] = 11",  vec![(8,  73)]),
@@ -165,7 +189,7 @@ mod tests {
         ];
 
         for (s, expected) in cases {
-            match parse(s) {
+            match parse(s).unwrap() {
                 Op::Mem { adr, val } => {
                     assert_eq!(mask1(&m, adr, val), expected);
                 },
@@ -183,7 +207,7 @@ mod tests {
             "mem[8] = 0",
         ];
 
-        let ops: Vec<Op> = lines.iter().map(|line| parse(*line)).collect();
+        let ops: Vec<Op> = lines.iter().map(|line| parse(*line).unwrap()).collect();
         let map = exec(&ops, mask1);
         let sum = map.iter().fold(0u64, |acc, (_, v)| acc + *v);
 
@@ -212,7 +236,7 @@ mod tests {
 
     #[test]
     fn test_mask2_one() {
-        let m = parse("mask = 000000000000000000000000000000X1001X");
+        let m = parse("mask = 000000000000000000000000000000X1001X").unwrap();
         assert_eq!(mask2(&m, 42, 100), vec![
             (26, 100),
             (27, 100),
@@ -223,7 +247,7 @@ mod tests {
 
     #[test]
     fn test_mask2_two() {
-        let m = parse("mask = 00000000000000000000000000000000X0XX");
+        let m = parse("mask = 00000000000000000000000000000000X0XX").unwrap();
         assert_eq!(mask2(&m, 26, 1), vec![
             (16, 1),
             (17, 1),
@@ -245,7 +269,7 @@ mod tests {
             "mem[26] = 1",
         ];
 
-        let ops: Vec<Op> = lines.iter().map(|line| parse(*line)).collect();
+        let ops: Vec<Op> = lines.iter().map(|line| parse(*line).unwrap()).collect();
         let map = exec(&ops, mask2);
         let sum = map.iter().fold(0u64, |acc, (_, v)| acc + *v);
 
@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use crate::error::{number, Result};
+use crate::solution::Solution;
 
 fn play(input: &[usize], mut goal: usize) -> usize {
     let mut number: usize = 0;
@@ -36,12 +38,32 @@ fn play(input: &[usize], mut goal: usize) -> usize {
     number
 }
 
-pub fn main() {
-    let answer = play(&[20,9,11,0,1,2], 2020);
-    println!("{}", answer);
+fn input(text: &str) -> Result<Vec<usize>> {
+    text.trim()
+        .split(',')
+        .map(number)
+        .collect::<Result<Vec<usize>>>()
+        .map_err(|e| e.at(1))
+}
+
+pub struct Day15;
+
+impl Solution for Day15 {
+    type Input = Vec<usize>;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
+
+    fn part1(input: &Self::Input) -> usize {
+        play(input, 2020)
+    }
 
-    let answer = play(&[20,9,11,0,1,2], 30_000_000);
-    println!("{}", answer);
+    fn part2(input: &Self::Input) -> usize {
+        play(input, 30_000_000)
+    }
 }
 
 #[cfg(test)]
@@ -1,51 +1,63 @@
-use crate::utils::lines;
+#![allow(clippy::map_clone, clippy::needless_borrow, clippy::ptr_arg, clippy::useless_conversion)]
+
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
+use crate::input::Input;
 use std::collections::HashSet;
 
 type Rule = (String, u64, u64, u64, u64);
 
 type Ticket = Vec<u64>;
 
-fn parse_rule(line: &str) -> Rule {
-    let mut split1 = line.split(": ");
-    let name = split1.next().unwrap().to_string();
-    let mut split2 = split1.next().unwrap().split(" or ");
-    let mut split3 = split2.next().unwrap().split("-");
-    let mut split4 = split2.next().unwrap().split("-");
-    let a: u64 = split3.next().unwrap().parse().unwrap();
-    let b: u64 = split3.next().unwrap().parse().unwrap();
-    let c: u64 = split4.next().unwrap().parse().unwrap();
-    let d: u64 = split4.next().unwrap().parse().unwrap();
-    (name, a, b, c, d)
+fn parse_rule(line: &str) -> Result<Rule> {
+    let invalid = || Error::new(line, "expected `name: a-b or c-d`");
+    let (name, ranges) = line.split_once(": ").ok_or_else(invalid)?;
+    let (lo, hi) = ranges.split_once(" or ").ok_or_else(invalid)?;
+    let (a, b) = lo.split_once('-').ok_or_else(invalid)?;
+    let (c, d) = hi.split_once('-').ok_or_else(invalid)?;
+    Ok((name.to_string(), number(a)?, number(b)?, number(c)?, number(d)?))
 }
 
-fn parse_ticket(line: &str) -> Ticket {
-    line.split(",")
-        .map(|number| number.parse().unwrap())
+fn parse_ticket(line: &str) -> Result<Ticket> {
+    line.split(',')
+        .map(number)
         .collect()
 }
 
-fn parse_input(lines: Vec<String>) -> (Vec<Rule>, Ticket, Vec<Ticket>) {
-    let mut split = lines
-        .split(|line| line.is_empty());
+// Parse tickets of the section following given header, checking each one has a value for every rule.
+fn parse_tickets(section: Option<&(usize, &[String])>, header: &str, rules: usize) -> Result<Vec<Ticket>> {
+    let (start, lines) = section
+        .ok_or_else(|| Error::new("", format!("missing `{}` section", header)))?;
+    if lines.first().map(|line| line.as_str()) != Some(header) {
+        let line = lines.first().map(|line| line.as_str()).unwrap_or_default();
+        return Err(Error::new(line, format!("expected `{}`", header)).at(start + 1));
+    }
 
-    let rules = split.next().unwrap()
-        .into_iter()
-        .map(|line| parse_rule(&line))
-        .collect();
+    parse_lines(lines.iter().skip(1).map(|line| line.as_str()), |line| {
+        let ticket = parse_ticket(line)?;
+        if ticket.len() < rules {
+            return Err(Error::new(line, format!("expected at least {} values", rules)));
+        }
+        Ok(ticket)
+    }).map_err(|e| e.offset(start + 1))
+}
+
+fn parse_input(input: &Input) -> Result<(Vec<Rule>, Ticket, Vec<Ticket>)> {
+    let sections = input.paragraphs();
 
-    let ticket = split.next().unwrap()
+    let (start, lines) = sections.first()
+        .ok_or_else(|| Error::new("", "missing rules"))?;
+    let rules = parse_lines(lines.iter().map(|line| line.as_str()), parse_rule)
+        .map_err(|e| e.offset(*start))?;
+
+    let ticket = parse_tickets(sections.get(1), "your ticket:", rules.len())?
         .into_iter()
-        .skip(1)
         .next()
-        .unwrap();
+        .ok_or_else(|| Error::new("your ticket:", "missing ticket"))?;
 
-    let tickets = split.next().unwrap()
-        .into_iter()
-        .skip(1)
-        .map(|line| parse_ticket(line))
-        .collect();
+    let tickets = parse_tickets(sections.get(2), "nearby tickets:", rules.len())?;
 
-    (rules, parse_ticket(ticket), tickets)
+    Ok((rules, ticket, tickets))
 }
 
 fn is_valid(rule: &Rule, val: u64) -> bool {
@@ -122,28 +134,39 @@ fn resolve(reduced: Vec<HashSet<usize>>) -> Vec<usize> {
     collect(helper(reduced, Vec::new()))
 }
 
-pub fn main() {
-    let (rules, own, tickets) = parse_input(lines());
-
-    let sum: u64 = tickets.iter()
-        .map(|ticket| validate(&rules, ticket).into_iter().sum::<u64>())
-        .sum();
-    println!("{}", sum);
-
-    let valid: Vec<Ticket> = tickets.into_iter()
-        .filter(|ticket| validate(&rules, ticket).is_empty())
-        .collect();
-
-    let reduced = reduce(&rules, &valid);
-    let resolved = resolve(reduced);
-    let values: Vec<u64> = rules.iter().enumerate()
-        .filter(|(_, r)| r.0.starts_with("departure"))
-        .map(|(i, _)| i)
-        .map(|i| resolved[i])
-        .map(|i| own[i])
-        .collect();
-    let mul = values.iter().fold(1u64, |acc, x| acc * x);
-    println!("{}", mul);
+pub struct Day16;
+
+impl Solution for Day16 {
+    type Input = (Vec<Rule>, Ticket, Vec<Ticket>);
+    type Part1 = u64;
+    type Part2 = u64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        parse_input(&Input::from(text))
+    }
+
+    fn part1((rules, _, tickets): &Self::Input) -> u64 {
+        tickets.iter()
+            .map(|ticket| validate(rules, ticket).into_iter().sum::<u64>())
+            .sum()
+    }
+
+    fn part2((rules, own, tickets): &Self::Input) -> u64 {
+        let valid: Vec<Ticket> = tickets.iter()
+            .filter(|ticket| validate(rules, ticket).is_empty())
+            .cloned()
+            .collect();
+
+        let reduced = reduce(rules, &valid);
+        let resolved = resolve(reduced);
+        let values: Vec<u64> = rules.iter().enumerate()
+            .filter(|(_, r)| r.0.starts_with("departure"))
+            .map(|(i, _)| i)
+            .map(|i| resolved[i])
+            .map(|i| own[i])
+            .collect();
+        values.iter().product::<u64>()
+    }
 }
 
 #[cfg(test)]
@@ -153,7 +176,7 @@ mod tests {
     #[test]
     fn test_parse_ticket() {
         let line = "row: 45-461 or 467-955";
-        assert_eq!(parse_rule(line), ("row".to_string(), 45, 461, 467, 955));
+        assert_eq!(parse_rule(line), Ok(("row".to_string(), 45, 461, 467, 955)));
     }
 
     #[test]
@@ -171,7 +194,7 @@ mod tests {
         ];
 
         let (rules, ticket, tickets) =
-            parse_input(lines.into_iter().map(|s| s.to_string()).collect());
+            parse_input(&Input::from(lines.join("\n").as_str())).unwrap();
 
         assert_eq!(rules, vec![
             ("wagon".to_string(), 38, 111, 127, 963),
@@ -185,6 +208,21 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_parse_input_invalid() {
+        let parse = |text: &str| parse_input(&Input::from(text)).unwrap_err();
+
+        let e = parse("a: 1-3 or 5-7\nb: 1 or 2\n");
+        assert_eq!(e.line(), Some(2));
+
+        let e = parse("a: 1-3 or 5-7\n\nyour ticket:\n7\n");
+        assert_eq!(e.reason(), "missing `nearby tickets:` section");
+
+        let e = parse("a: 1-3 or 5-7\nb: 6-11 or 33-44\n\nyour ticket:\n7,1\n\nnearby tickets:\n7,3\n40\n");
+        assert_eq!(e.line(), Some(9));
+        assert_eq!(e.text(), "40");
+    }
+
     #[test]
     fn test_validate() {
         let rules = vec![
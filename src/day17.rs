@@ -1,4 +1,9 @@
-use crate::utils::lines;
+#![allow(clippy::clone_on_copy, clippy::redundant_closure, clippy::useless_conversion)]
+
+use crate::error::Result;
+use crate::grid::Grid;
+use crate::solution::Solution;
+use crate::input::Input;
 use std::collections::{HashSet, HashMap};
 
 type Cell = (i32, i32, i32, i32);
@@ -8,17 +13,17 @@ type State = HashSet<Cell>;
 type Index = HashMap<Cell, u32>;
 
 
-fn parse(lines: Vec<String>) -> Vec<Cell> {
-    lines.into_iter()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.chars().into_iter()
-                .enumerate()
-                .filter(|(_, c)| *c == '#')
-                .map(|(x, _)| (x as i32, y as i32, 0i32, 0i32))
-                .collect::<Vec<_>>()
-        })
-        .collect()
+fn parse(lines: &[String]) -> Result<Vec<Cell>> {
+    let grid = Grid::parse(lines.iter().map(|line| line.as_str()), |c| match c {
+        '.' => Some(false),
+        '#' => Some(true),
+        _ => None,
+    })?;
+
+    Ok(grid.positions()
+        .filter(|pos| grid[*pos])
+        .map(|(y, x)| (x as i32, y as i32, 0i32, 0i32))
+        .collect())
 }
 
 fn adj3(cell: &Cell) -> Vec<Cell> {
@@ -75,28 +80,36 @@ fn apply(state: &State, index: &Index) -> State {
         .collect()
 }
 
-pub fn main() {
-    let state = parse(lines())
-        .into_iter()
-        .collect::<HashSet<_>>();
+pub struct Day17;
 
-    let count = (0..6)
-        .into_iter()
-        .fold(state.clone(), |state, _| {
-            let idx = index(&state, adj3);
-            apply(&state, &idx)
-        })
-        .len();
-    println!("{}", count);
+impl Solution for Day17 {
+    type Input = State;
+    type Part1 = usize;
+    type Part2 = usize;
 
-    let count = (0..6)
-        .into_iter()
-        .fold(state.clone(), |state, _| {
-            let idx = index(&state, adj4);
-            apply(&state, &idx)
-        })
-        .len();
-    println!("{}", count);
+    fn parse(text: &str) -> Result<Self::Input> {
+        Ok(parse(Input::from(text).lines())?
+            .into_iter()
+            .collect::<HashSet<_>>())
+    }
+
+    fn part1(state: &Self::Input) -> usize {
+        (0..6)
+            .fold(state.clone(), |state, _| {
+                let idx = index(&state, adj3);
+                apply(&state, &idx)
+            })
+            .len()
+    }
+
+    fn part2(state: &Self::Input) -> usize {
+        (0..6)
+            .fold(state.clone(), |state, _| {
+                let idx = index(&state, adj4);
+                apply(&state, &idx)
+            })
+            .len()
+    }
 }
 
 #[cfg(test)]
@@ -111,7 +124,7 @@ mod tests {
             "###",
         ];
 
-        let parsed = parse(lines.into_iter().map(|s| s.to_owned()).collect());
+        let parsed = parse(&lines.into_iter().map(|s| s.to_owned()).collect::<Vec<_>>()).unwrap();
         assert_eq!(parsed, vec![
             (1, 0, 0, 0),
             (2, 1, 0, 0),
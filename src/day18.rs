@@ -1,4 +1,12 @@
-use crate::utils::lines;
+#![allow(
+    clippy::into_iter_on_ref,
+    clippy::ptr_arg,
+    clippy::redundant_closure,
+    clippy::useless_format,
+)]
+
+use crate::error::{parse_lines, Error, Result};
+use crate::solution::Solution;
 
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -11,33 +19,64 @@ enum Token {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
-enum Op {
+pub enum Op {
     Mul,
     Add,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
-enum Expr {
+pub enum Expr {
     Const(i64),
     Op(Op),
     Group(Vec<Expr>),
 }
 
 
-fn tokenize(line: &str) -> Vec<Token> {
+fn tokenize(line: &str) -> Result<Vec<Token>> {
     line.chars()
-        .into_iter()
         .filter(|c| *c != ' ')
         .map(|s| match s {
-            '+' => Token::Add,
-            '*' => Token::Mul,
-            '(' => Token::Open,
-            ')' => Token::Close,
-            n  => Token::Num(n as i64 - '0' as i64)
+            '+' => Ok(Token::Add),
+            '*' => Ok(Token::Mul),
+            '(' => Ok(Token::Open),
+            ')' => Ok(Token::Close),
+            n if n.is_ascii_digit() => Ok(Token::Num(n as i64 - '0' as i64)),
+            _ => Err(Error::new(line, format!("unexpected character {:?}", s)))
         })
         .collect()
 }
 
+// Operands and operators must alternate, and parentheses must be balanced.
+fn check(line: &str, tokens: &[Token]) -> Result<()> {
+    let mut operand = true;
+    let mut depth = 0;
+    for token in tokens {
+        match token {
+            Token::Num(_) | Token::Open if !operand => return Err(Error::new(line, "missing operator")),
+            Token::Close | Token::Add | Token::Mul if operand => return Err(Error::new(line, "missing operand")),
+            Token::Close if depth == 0 => return Err(Error::new(line, "unbalanced parentheses")),
+            Token::Num(_) => operand = false,
+            Token::Open => depth += 1,
+            Token::Close => depth -= 1,
+            Token::Add | Token::Mul => operand = true,
+        }
+    }
+
+    if operand {
+        Err(Error::new(line, "missing operand"))
+    } else if depth > 0 {
+        Err(Error::new(line, "unbalanced parentheses"))
+    } else {
+        Ok(())
+    }
+}
+
+fn expr(line: &str) -> Result<Expr> {
+    let tokens = tokenize(line)?;
+    check(line, &tokens)?;
+    Ok(parse(tokens))
+}
+
 fn group(tokens: &Vec<Token>, mut idx: usize, mut acc: Vec<Expr>) -> (usize, Expr) {
     while idx < tokens.len() {
         let token = tokens[idx].to_owned();
@@ -221,24 +260,30 @@ fn eval2(expr: Expr) -> i64 {
     }
 }
 
-pub fn main() {
-    let expr = lines()
-        .into_iter()
-        .map(|line| tokenize(&line))
-        .map(|tokens| parse(tokens))
-        .collect::<Vec<_>>();
-
-    let sum = expr.iter()
-        .cloned()
-        .map(|expr| eval1(expr))
-        .sum::<i64>();
-    println!("{}", sum);
-
-    let sum = expr.iter()
-        .cloned()
-        .map(|expr| eval2(expr))
-        .sum::<i64>();
-    println!("{}", sum);
+pub struct Day18;
+
+impl Solution for Day18 {
+    type Input = Vec<Expr>;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        parse_lines(text.lines(), expr)
+    }
+
+    fn part1(expr: &Self::Input) -> i64 {
+        expr.iter()
+            .cloned()
+            .map(eval1)
+            .sum::<i64>()
+    }
+
+    fn part2(expr: &Self::Input) -> i64 {
+        expr.iter()
+            .cloned()
+            .map(eval2)
+            .sum::<i64>()
+    }
 }
 
 #[cfg(test)]
@@ -256,7 +301,7 @@ mod tests {
         ];
 
         for (s, expected) in cases {
-            let result = eval1(parse(tokenize(s)));
+            let result = eval1(parse(tokenize(s).unwrap()));
             assert_eq!(result, expected);
         }
     }
@@ -273,14 +318,14 @@ mod tests {
         ];
 
         for (s, expected) in cases {
-            let result = eval2(parse(tokenize(s)));
+            let result = eval2(parse(tokenize(s).unwrap()));
             assert_eq!(result, expected, "{:?}", s);
         }
     }
 
     #[test]
     fn test_once1() {
-        let expr = match parse(tokenize("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))")) {
+        let expr = match parse(tokenize("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))").unwrap()) {
             Expr::Group(items) => items,
             _ => unreachable!()
         };
@@ -413,7 +458,7 @@ mod tests {
 
     #[test]
     fn test_tokenize1() {
-        assert_eq!(tokenize("(6 + 5) * 6"), vec![
+        assert_eq!(tokenize("(6 + 5) * 6").unwrap(), vec![
             Token::Open, Token::Num(6), Token::Add, Token::Num(5), Token::Close,
             Token::Mul, Token::Num(6)
         ]);
@@ -421,7 +466,7 @@ mod tests {
 
     #[test]
     fn test_tokenize2() {
-        let tokens = tokenize("(8 + 4 * (2 * 9) + 6 + 6 + 3) + 4");
+        let tokens = tokenize("(8 + 4 * (2 * 9) + 6 + 6 + 3) + 4").unwrap();
         assert_eq!(tokens, vec![
             Token::Open,
                 Token::Num(8),
@@ -442,6 +487,24 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_expr_invalid() {
+        let cases = vec![
+            ("1 + x", "unexpected character 'x'"),
+            ("1 + ", "missing operand"),
+            ("* 2", "missing operand"),
+            ("()", "missing operand"),
+            ("1 2", "missing operator"),
+            ("2 (3)", "missing operator"),
+            ("(1 + 2", "unbalanced parentheses"),
+            ("1 + 2)", "unbalanced parentheses"),
+        ];
+
+        for (s, reason) in cases {
+            assert_eq!(expr(s).unwrap_err().reason(), reason, "{:?}", s);
+        }
+    }
+
     #[test]
     fn test_group1() {
         let tokens = vec![
@@ -1,60 +1,88 @@
-use crate::utils::lines;
+#![allow(clippy::into_iter_on_ref, clippy::manual_try_fold)]
+
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
+use crate::input::Input;
 use std::collections::{HashMap, HashSet};
 
 type Id = usize;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
-enum Rule {
+pub enum Rule {
     Char(char),
     Seq(Vec<Id>),
     Or(Vec<Rule>),
 }
 
-fn parse(line: &str) -> (Id, Rule) {
-    fn seq(s: &str) -> Vec<Id> {
-        s.split(" ")
-            .into_iter()
-            .map(|id| id.parse::<Id>().unwrap())
+fn parse(line: &str) -> Result<(Id, Rule)> {
+    fn seq(s: &str) -> Result<Vec<Id>> {
+        s.split(' ')
+            .map(number::<Id>)
             .collect()
     }
 
-    let mut split = line.split(": ");
-    let id: Id = split.next().unwrap().parse().unwrap();
+    let (id, rest) = line.split_once(": ")
+        .ok_or_else(|| Error::new(line, "expected `id: rule`"))?;
+    let id: Id = number(id)?;
 
-    let rest = split.next().unwrap();
     let rule = if rest.contains('\"') {
-        let c = rest.chars().skip(1).next().unwrap();
-        Rule::Char(c)
+        let chars: Vec<char> = rest.chars().collect();
+        if chars.len() != 3 || chars[0] != '"' || chars[2] != '"' {
+            return Err(Error::new(line, "expected a single quoted character"));
+        }
+        Rule::Char(chars[1])
     } else if rest.contains('|') {
-        let mut it = rest.split(" | ");
-        Rule::Or(vec![
-            Rule::Seq(seq(it.next().unwrap())),
-            Rule::Seq(seq(it.next().unwrap())),
-        ])
+        let alternatives = rest.split(" | ")
+            .map(|s| seq(s).map(Rule::Seq))
+            .collect::<Result<Vec<_>>>()?;
+        Rule::Or(alternatives)
     } else {
-        Rule::Seq(seq(rest))
+        Rule::Seq(seq(rest)?)
     };
 
-    (id, rule)
+    Ok((id, rule))
+}
+
+// Ids of all the rules referenced by given one.
+fn refs(rule: &Rule) -> Vec<Id> {
+    match rule {
+        Rule::Char(_) => vec![],
+        Rule::Seq(ids) => ids.clone(),
+        Rule::Or(rules) => rules.iter().flat_map(refs).collect(),
+    }
 }
 
-fn input(lines: &Vec<String>) -> (HashMap<Id, Rule>, Vec<String>) {
-    let mut it = lines.split(|line| line.is_empty());
+const REQUIRED: [Id; 3] = [0, 31, 42];
+
+fn input(input: &Input) -> Result<(HashMap<Id, Rule>, Vec<String>)> {
+    let sections = input.paragraphs();
 
-    let rules = it.next().unwrap()
-        .into_iter()
-        .map(|s| parse(s))
+    let (start, lines) = sections.first()
+        .ok_or_else(|| Error::new("", "missing rules"))?;
+    let parsed = parse_lines(lines.iter().map(|s| s.as_str()), parse)
+        .map_err(|e| e.offset(*start))?;
+    let ids: HashSet<Id> = parsed.iter().map(|(id, _)| *id).collect();
+    for (i, (_, rule)) in parsed.iter().enumerate() {
+        if let Some(id) = refs(rule).into_iter().find(|id| !ids.contains(id)) {
+            return Err(Error::new(&lines[i], format!("unknown rule {}", id)).at(start + i + 1));
+        }
+    }
+    // Both parts start from these.
+    if let Some(id) = REQUIRED.iter().find(|id| !ids.contains(id)) {
+        return Err(Error::new("", format!("missing rule {}", id)));
+    }
+
+    let rules = parsed.into_iter()
         .fold(HashMap::new(), |mut map, (id, rule)| {
             map.insert(id, rule);
             map
         });
 
-    let remaining = it.next().unwrap()
-        .into_iter()
-        .cloned()
-        .collect();
+    let remaining = sections.get(1)
+        .map(|(_, lines)| lines.to_vec())
+        .unwrap_or_default();
 
-    (rules, remaining)
+    Ok((rules, remaining))
 }
 
 fn build1(rule: &Rule, map: &HashMap<Id, Rule>) -> Vec<String> {
@@ -107,7 +135,7 @@ fn apply(line: String, rule: &Rule, map: &HashMap<Id, Rule>) -> Option<String> {
             }
         }
         Rule::Seq(ids) => {
-            ids.iter()
+            ids.into_iter()
                 .fold(Some(line), |opt, id| {
                     opt.into_iter()
                         .flat_map(|s| {
@@ -127,6 +155,16 @@ fn apply(line: String, rule: &Rule, map: &HashMap<Id, Rule>) -> Option<String> {
     }
 }
 
+// Line split into pieces of `width` bytes, if it splits evenly.
+fn chunks(line: &str, width: usize) -> Option<Vec<&str>> {
+    if width == 0 || !line.len().is_multiple_of(width) {
+        return None;
+    }
+    (0..line.len() / width)
+        .map(|i| line.get(i * width..(i + 1) * width))
+        .collect()
+}
+
 #[allow(dead_code)]
 fn verify(line: &str, id: Id, map: &HashMap<Id, Rule>) -> bool {
     let rule = map.get(&id).unwrap();
@@ -135,66 +173,44 @@ fn verify(line: &str, id: Id, map: &HashMap<Id, Rule>) -> bool {
         .unwrap_or_default()
 }
 
-pub fn main() {
-    let lines = lines();
-    let (rules, inputs) = input(&lines);
-
-    let r0 = rules.get(&0).unwrap();
-    let all = build1(r0, &rules)
-        .into_iter()
-        .collect::<HashSet<_>>();
-
-    let n = inputs.iter()
-        .filter(|s| all.contains(*s))
-        .count();
-    println!("{}", n); // 132
-
-    let r31 = rules.get(&31).unwrap();
-    let v31 = build1(r31, &rules).into_iter().collect::<HashSet<_>>();
-
-    let r42 = rules.get(&42).unwrap();
-    let v42 = build1(r42, &rules).into_iter().collect::<HashSet<_>>();
-
-    let n = inputs.iter()
-        .filter(|s| {
-            let n = s.len();
-            let a = &s[0..8];
-            let b = &s[8..16];
-            let z = &s[n-8..];
-            v42.contains(a) && v42.contains(b) && v31.contains(z)
-        })
-        .map(|s| {
-            let n = s.len();
-            s[16..(n-8)].to_string()
-        })
-        .filter(|s| {
-            let mut z: &str = s;
-            let mut n42: usize = 0;
-            while z.len() > 0 {
-                let t = &z[0..8];
-                if v42.contains(t) {
-                    z = &z[8..];
-                    n42 += 1;
-                } else {
-                    break;
-                }
-            }
+pub struct Day19;
 
-            let mut n31: usize = 0;
-            while z.len() > 0 {
-                let t = &z[0..8];
-                if v31.contains(t) {
-                    z = &z[8..];
-                    n31 += 1;
-                } else {
-                    break;
-                }
-            }
+impl Solution for Day19 {
+    type Input = (HashMap<Id, Rule>, Vec<String>);
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(&Input::from(text))
+    }
+
+    fn part1((rules, inputs): &Self::Input) -> usize {
+        let all = build1(&rules[&0], rules)
+            .into_iter()
+            .collect::<HashSet<_>>();
 
-            z.is_empty() && n42 >= n31
-        })
-        .count();
-    println!("{}", n); // 306
+        inputs.iter()
+            .filter(|s| all.contains(*s))
+            .count()
+    }
+
+    // Rule 0 is `8 11`, with loops `8: 42 | 42 8` and `11: 42 31 | 42 11 31`: some 42s followed by
+    // fewer 31s, at least one.
+    fn part2((rules, inputs): &Self::Input) -> usize {
+        let v31 = build1(&rules[&31], rules).into_iter().collect::<HashSet<_>>();
+        let v42 = build1(&rules[&42], rules).into_iter().collect::<HashSet<_>>();
+        // Strings of rules 42 and 31 have the same length, 8 for the puzzle input.
+        let width = v42.iter().next().map_or(0, |s| s.len());
+
+        inputs.iter()
+            .filter_map(|s| chunks(s, width))
+            .filter(|chunks| {
+                let n42 = chunks.iter().take_while(|t| v42.contains(**t)).count();
+                let n31 = chunks[n42..].iter().take_while(|t| v31.contains(**t)).count();
+                n42 + n31 == chunks.len() && n31 >= 1 && n42 > n31
+            })
+            .count()
+    }
 }
 
 #[cfg(test)]
@@ -214,10 +230,36 @@ mod tests {
         ];
 
         for (s, (id, rule)) in cases {
-            assert_eq!(parse(s), (id, rule), "{}", s)
+            assert_eq!(parse(s), Ok((id, rule)), "{}", s)
         }
     }
 
+    #[test]
+    fn test_input_invalid() {
+        let lines = ["0: 1 2", "1: \"a\"", "", "ab"];
+        let e = input(&Input::from(lines.join("\n").as_str())).unwrap_err();
+        assert_eq!(e.line(), Some(1));
+        assert_eq!(e.reason(), "unknown rule 2");
+
+        let e = input(&Input::from("0: 1\n1: \"a\"\n31: 1\n\nab")).unwrap_err();
+        assert_eq!(e.reason(), "missing rule 42");
+        let e = input(&Input::from("31: 42\n42: \"a\"\n\nab")).unwrap_err();
+        assert_eq!(e.reason(), "missing rule 0");
+
+        assert!(parse("1: a").is_err());
+        assert!(parse("1: \"ab\"").is_err());
+        assert!(parse("x: 1 2").is_err());
+    }
+
+    #[test]
+    fn test_part2() {
+        let rules = "0: 8 11\n8: 42\n11: 42 31\n42: \"a\"\n31: \"b\"";
+        let messages = ["aab", "aaabb", "ab", "b", "aabb", "abab", "aaaab", "aé"];
+        let input = Day19::parse(&format!("{}\n\n{}", rules, messages.join("\n"))).unwrap();
+        assert_eq!(Day19::part1(&input), 1);
+        assert_eq!(Day19::part2(&input), 3);
+    }
+
     #[test]
     fn test_verify() {
         let lines = vec![
@@ -230,7 +272,9 @@ mod tests {
             "",
         ];
 
-        let (rules, _) = input(&lines.iter().map(|s| s.to_string()).collect());
+        let rules = parse_lines(lines.into_iter().filter(|s| !s.is_empty()), parse).unwrap()
+            .into_iter()
+            .collect::<HashMap<_, _>>();
 
         assert!(verify("ababbb", 0, &rules));
         assert!(verify("abbbab", 0, &rules));
@@ -1,127 +1,87 @@
-use crate::utils::lines;
+#![allow(
+    clippy::clone_on_copy,
+    clippy::collapsible_if,
+    clippy::filter_next,
+    clippy::into_iter_on_ref,
+    clippy::ptr_arg,
+    clippy::redundant_closure,
+    clippy::useless_conversion,
+)]
+
+use crate::error::{number, Error, Result};
+use crate::grid::Grid;
+use crate::solution::Solution;
+use crate::input::Input;
 use std::collections::HashMap;
 
 #[derive(Clone)]
-struct Tile {
+pub struct Tile {
     id: u64,
-    tile: Vec<Vec<char>>,
+    tile: Grid<char>,
 }
 
 impl Tile {
-    fn vflip(&self) -> Tile {
-        let tile = self.tile
-            .iter()
-            .cloned()
-            .map(|row| row.into_iter().rev().collect())
-            .collect();
-
+    fn with(&self, tile: Grid<char>) -> Tile {
         Tile {
             id: self.id,
             tile
         }
     }
 
+    fn vflip(&self) -> Tile {
+        self.with(self.tile.vflip())
+    }
+
     #[allow(dead_code)]
     fn hflip(&self) -> Tile {
-        let tile = self.tile
-            .iter()
-            .cloned()
-            .rev()
-            .collect();
-
-        Tile {
-            id: self.id,
-            tile
-        }
+        self.with(self.tile.hflip())
     }
 
     fn cw(&self) -> Tile {
-        let n = self.tile.len();
-
-        let tile = (0..n).into_iter()
-            .map(|i| self.tile
-                .iter()
-                .map(|vec| vec.get(i).unwrap())
-                .cloned()
-                .rev()
-                .collect())
-            .collect();
-
-        Tile {
-            id: self.id,
-            tile
-        }
+        self.with(self.tile.cw())
     }
 
     #[allow(dead_code)]
     fn ccw(&self) -> Tile {
-        let n = self.tile.len();
-
-        let tile = (0..n).into_iter()
-            .map(|i| self.tile
-                .iter()
-                .map(|vec| vec.get(i).unwrap())
-                .cloned()
-                .collect())
-            .rev()
-            .collect();
-
-        Tile {
-            id: self.id,
-            tile
-        }
+        self.with(self.tile.ccw())
     }
 
     fn hfit(&self, lft: &Tile) -> bool {
-        let a = lft.tile.iter()
-            .map(|v| v.last().unwrap());
-
-        let b = self.tile.iter()
-            .map(|v| v.get(0).unwrap());
-
-        a.zip(b).all(|(x, y)| x == y)
+        let a = lft.tile.col(lft.tile.cols() - 1);
+        let b = self.tile.col(0);
+        a.eq(b)
     }
 
     fn vfit(&self, top: &Tile) -> bool {
-        let a: &Vec<char> = top.tile.iter()
-            .last()
-            .unwrap();
-
-        let b: &Vec<char> = self.tile
-            .get(0)
-            .unwrap();
-
-        a == b
+        top.tile.row(top.tile.rows() - 1) == self.tile.row(0)
     }
 }
 
-fn parse(lines: &[String]) -> Tile {
-    let mut it = lines.iter();
-    let header = it.next().unwrap();
+fn parse(lines: &[String]) -> Result<Tile> {
+    let header = lines.first().map(|s| s.as_str()).unwrap_or_default();
+    let id: u64 = header.strip_prefix("Tile ")
+        .and_then(|s| s.strip_suffix(':'))
+        .ok_or_else(|| Error::new(header, "expected `Tile <id>:`").at(1))
+        .and_then(|id| number(id).map_err(|e| e.at(1)))?;
 
-    let id: u64 = header.chars()
-        .into_iter()
-        .skip_while(|c| !c.is_numeric())
-        .take_while(|c| c.is_numeric())
-        .collect::<String>()
-        .parse()
-        .unwrap();
-
-    let tile: Vec<Vec<char>> = it
-        .map(|s| s.chars().collect())
-        .collect();
+    if lines.len() < 2 {
+        return Err(Error::new(header, "empty tile").at(1));
+    }
+    let tile = Grid::parse(lines.iter().skip(1).map(|s| s.as_str()), |c| Some(c).filter(|c| *c == '.' || *c == '#'))
+        .map_err(|e| e.offset(1))?;
+    if tile.rows() != tile.cols() {
+        let line = lines.last().map(|s| s.as_str()).unwrap_or_default();
+        return Err(Error::new(line, "tile must be square").at(lines.len()));
+    }
 
-    Tile { id, tile }
+    Ok(Tile { id, tile })
 }
 
-fn input(lines: Vec<String>) -> Vec<Tile> {
-    lines.split(|s| s.is_empty())
-        .into_iter()
-        .map(|chunk| parse(chunk))
-        .collect()
+fn input(input: &Input) -> Result<Vec<Tile>> {
+    input.parse_paragraphs(parse)
 }
 
-fn pack(vec: &Vec<char>, one: char) -> u32 {
+fn pack(vec: &[char], one: char) -> u32 {
     assert!(vec.len() <= 32);
     vec.into_iter()
         .map(|c| if *c == one {1u32} else {0u32})
@@ -129,14 +89,15 @@ fn pack(vec: &Vec<char>, one: char) -> u32 {
 }
 
 fn edges(tile: &Tile) -> Vec<u32> {
-    let top = tile.tile.get(0).unwrap();
-    let bot = tile.tile.last().unwrap();
+    let n = tile.tile.rows();
+    let top = tile.tile.row(0);
+    let bot = tile.tile.row(n - 1);
 
-    let lft = tile.tile.iter()
-        .map(|row| row.get(0).unwrap().clone())
+    let lft = tile.tile.col(0)
+        .cloned()
         .collect::<Vec<_>>();
-    let rgt = tile.tile.iter()
-        .map(|row| row.last().unwrap().clone())
+    let rgt = tile.tile.col(n - 1)
+        .cloned()
         .collect::<Vec<_>>();
 
     vec![
@@ -146,10 +107,10 @@ fn edges(tile: &Tile) -> Vec<u32> {
         pack(&rgt, '#'),
 
         // each image tile has been rotated and flipped to a random orientation
-        pack(&top.iter().cloned().rev().collect(), '#'),
-        pack(&bot.iter().cloned().rev().collect(), '#'),
-        pack(&lft.iter().cloned().rev().collect(), '#'),
-        pack(&rgt.iter().cloned().rev().collect(), '#'),
+        pack(&top.iter().cloned().rev().collect::<Vec<_>>(), '#'),
+        pack(&bot.iter().cloned().rev().collect::<Vec<_>>(), '#'),
+        pack(&lft.iter().cloned().rev().collect::<Vec<_>>(), '#'),
+        pack(&rgt.iter().cloned().rev().collect::<Vec<_>>(), '#'),
     ]
 }
 
@@ -197,19 +158,8 @@ fn corners(index: &HashMap<u64, Vec<u32>>, count: &HashMap<u32, u32>) -> Vec<u64
 
 
 fn crop(tile: Tile) -> Tile {
-    let n = tile.tile.len();
-
-    let cropped = tile.tile
-        .into_iter()
-        .skip(1)
-        .take(n - 2)
-        .map(|row| row.into_iter().skip(1).take(n - 2).collect())
-        .collect();
-
-    Tile {
-        id: tile.id,
-        tile: cropped,
-    }
+    let n = tile.tile.rows();
+    tile.with(tile.tile.crop((1, 1), n - 2, n - 2))
 }
 
 fn iterate(tile: &Tile) -> Vec<Tile> {
@@ -265,45 +215,26 @@ fn align2(size: usize, grid: Vec<Tile>, queue: Vec<Tile>, idx: usize) -> Vec<Til
 }
 
 fn join(size: usize, aligned: &Vec<Tile>) -> Tile {
-    fn append(a: Tile, b: Tile) -> Tile {
-        let tile = a.tile.into_iter()
-            .zip(b.tile.into_iter())
-            .map(|(mut x, mut y)| {
-                x.append(&mut y);
-                x
-            })
-            .collect();
-
-        Tile {
-            id: 0,
-            tile
-        }
-    }
-
     let mut tile = vec![];
 
-    let mut it = &aligned[..];
-    while it.len() > 0 {
-        let chunk = &it[0..size];
-
-        let mut row = chunk[0].to_owned();
-        for i in 1..size {
-            row = append(row, chunk[i].to_owned());
+    for chunk in aligned.chunks(size) {
+        for r in 0..chunk[0].tile.rows() {
+            let row = chunk.iter()
+                .flat_map(|t| t.tile.row(r).iter().cloned())
+                .collect::<Vec<_>>();
+            tile.push(row);
         }
-        tile.append(&mut row.tile);
-
-        it = &it[size..];
     }
 
     Tile {
         id: 0,
-        tile,
+        tile: Grid::from_rows(tile),
     }
 }
 
-fn lookup(tile: &Vec<Vec<char>>, pattern: &Vec<Vec<char>>) -> usize {
-    let (t_rows, t_cols) = (tile.len(), tile.get(0).unwrap().len());
-    let (p_rows, p_cols) = (pattern.len(), pattern.get(0).unwrap().len());
+fn lookup(tile: &Grid<char>, pattern: &Grid<char>) -> usize {
+    let (t_rows, t_cols) = (tile.rows(), tile.cols());
+    let (p_rows, p_cols) = (pattern.rows(), pattern.cols());
 
     let mut count = 0;
     for r in 0..(t_rows + 1 - p_rows) {
@@ -312,8 +243,8 @@ fn lookup(tile: &Vec<Vec<char>>, pattern: &Vec<Vec<char>>) -> usize {
 
             for i in 0..p_rows {
                 for j in 0..p_cols {
-                    let t = char_at(tile, r + i, c + j);
-                    let p = char_at(pattern, i, j);
+                    let t = tile[(r + i, c + j)];
+                    let p = pattern[(i, j)];
                     if p == '#' {
                         if t != '#' {
                             continue 'outer;
@@ -328,72 +259,76 @@ fn lookup(tile: &Vec<Vec<char>>, pattern: &Vec<Vec<char>>) -> usize {
     count
 }
 
-fn char_at(tile: &Vec<Vec<char>>, row: usize, col: usize) -> char {
-    *tile.get(row).unwrap().get(col).unwrap()
-}
-
-fn chars(tile: &Vec<Vec<char>>, x: char) -> usize {
+fn chars(tile: &Grid<char>, x: char) -> usize {
     tile.iter()
-        .flat_map(|v| v)
         .filter(|c| **c == x)
         .count()
 }
 
-pub fn main() {
-    let tiles = input(lines());
-    let size = (tiles.len() as f64).sqrt().trunc() as usize;
+pub struct Day20;
 
-    let index = index(&tiles);
-    let count = count(&tiles);
+impl Solution for Day20 {
+    type Input = Vec<Tile>;
+    type Part1 = u64;
+    type Part2 = usize;
 
-    let corners = corners(&index, &count);
-    assert_eq!(corners.len(), 4);
-    let prod = corners.iter().product::<u64>();
-    println!("{}", prod);
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(&Input::from(text))
+    }
 
-    let aligned = corners.into_iter()
-        .map(|id| tiles.iter().find(|t| t.id == id).unwrap())
-        .flat_map(|tile| iterate(tile))
-        .map(|tile| {
-            let queue = tiles.iter()
-                .filter(|t| t.id != tile.id)
-                .cloned()
-                .collect::<Vec<_>>();
+    fn part1(tiles: &Self::Input) -> u64 {
+        let index = index(tiles);
+        let count = count(tiles);
 
-            let mut grid = Vec::with_capacity(size * size);
-            grid.push(tile.clone());
+        let corners = corners(&index, &count);
+        assert_eq!(corners.len(), 4);
+        corners.iter().product::<u64>()
+    }
 
-            align2(size, grid, queue, 1)
-        })
-        .find(|a| !a.is_empty())
-        .unwrap_or_default();
-    assert_eq!(aligned.len(), tiles.len());
-
-    let aligned = aligned.into_iter().map(crop).collect();
-    let joined = join(size, &aligned);
-
-    // let txt = joined.tile.iter()
-    //     .map(|row| row.iter().collect::<String>())
-    //     .collect::<Vec<_>>()
-    //     .join("\n");
-    // println!("{}", txt);
-
-    let pattern = vec![
-        "                  # ",
-        "#    ##    ##    ###",
-        " #  #  #  #  #  #   ",
-    ].into_iter()
-        .map(|row| row.chars().collect())
-        .collect();
-
-    let monsters = iterate(&joined)
-        .iter()
-        .map(|tile| lookup(&tile.tile, &pattern))
-        .max()
-        .unwrap();
-
-    let n = chars(&joined.tile, '#') - monsters * chars(&pattern, '#');
-    println!("{}", n);
+    fn part2(tiles: &Self::Input) -> usize {
+        let size = (tiles.len() as f64).sqrt().trunc() as usize;
+
+        let index = index(tiles);
+        let count = count(tiles);
+        let corners = corners(&index, &count);
+
+        let aligned = corners.into_iter()
+            .map(|id| tiles.iter().find(|t| t.id == id).unwrap())
+            .flat_map(iterate)
+            .map(|tile| {
+                let queue = tiles.iter()
+                    .filter(|t| t.id != tile.id)
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                let mut grid = Vec::with_capacity(size * size);
+                grid.push(tile.clone());
+
+                align2(size, grid, queue, 1)
+            })
+            .find(|a| !a.is_empty())
+            .unwrap_or_default();
+        assert_eq!(aligned.len(), tiles.len());
+
+        let aligned = aligned.into_iter().map(crop).collect::<Vec<_>>();
+        let joined = join(size, &aligned);
+
+        // println!("{}", joined.tile);
+
+        let pattern = Grid::parse(vec![
+            "                  # ",
+            "#    ##    ##    ###",
+            " #  #  #  #  #  #   ",
+        ], Some).unwrap();
+
+        let monsters = iterate(&joined)
+            .iter()
+            .map(|tile| lookup(&tile.tile, &pattern))
+            .max()
+            .unwrap();
+
+        chars(&joined.tile, '#') - monsters * chars(&pattern, '#')
+    }
 }
 
 #[cfg(test)]
@@ -414,99 +349,99 @@ mod tests {
     }
 
     fn to_tile(lines: Vec<&str>) -> Tile {
-        parse(&lines.into_iter().map(|s| s.to_string()).collect::<Vec<_>>())
+        parse(&lines.into_iter().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap()
     }
 
     #[test]
     fn test_vflip() {
         let tile = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['1', '2', '3'],
                 vec!['4', '5', '6'],
                 vec!['7', '8', '9'],
-            ],
+            ]),
         };
 
-        assert_eq!(tile.vflip().tile, vec![
+        assert_eq!(tile.vflip().tile, Grid::from_rows(vec![
             vec!['3', '2', '1'],
             vec!['6', '5', '4'],
             vec!['9', '8', '7'],
-        ]);
+        ]));
     }
 
     #[test]
     fn test_hflip() {
         let tile = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['1', '2', '3'],
                 vec!['4', '5', '6'],
                 vec!['7', '8', '9'],
-            ],
+            ]),
         };
 
-        assert_eq!(tile.hflip().tile, vec![
+        assert_eq!(tile.hflip().tile, Grid::from_rows(vec![
             vec!['7', '8', '9'],
             vec!['4', '5', '6'],
             vec!['1', '2', '3'],
-        ]);
+        ]));
     }
 
     #[test]
     fn test_cw() {
         let tile = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['1', '2', '3'],
                 vec!['4', '5', '6'],
                 vec!['7', '8', '9'],
-            ],
+            ]),
         };
 
-        assert_eq!(tile.cw().tile, vec![
+        assert_eq!(tile.cw().tile, Grid::from_rows(vec![
             vec!['7', '4', '1'],
             vec!['8', '5', '2'],
             vec!['9', '6', '3'],
-        ]);
+        ]));
     }
 
     #[test]
     fn test_ccw() {
         let tile = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['1', '2', '3'],
                 vec!['4', '5', '6'],
                 vec!['7', '8', '9'],
-            ],
+            ]),
         };
 
-        assert_eq!(tile.ccw().tile, vec![
+        assert_eq!(tile.ccw().tile, Grid::from_rows(vec![
             vec!['3', '6', '9'],
             vec!['2', '5', '8'],
             vec!['1', '4', '7'],
-        ]);
+        ]));
     }
 
     #[test]
     fn test_hfit() {
         let tile1 = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['a', 'b', 'x'],
                 vec!['c', 'd', 'x'],
                 vec!['e', 'f', 'x'],
-            ],
+            ]),
         };
 
         let tile2 = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['x', 'b', 'u'],
                 vec!['x', 'd', 'v'],
                 vec!['x', 'f', 'w'],
-            ],
+            ]),
         };
 
         assert!(tile2.hfit(&tile1));
@@ -516,25 +451,35 @@ mod tests {
     fn test_vfit() {
         let tile1 = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['x', 'x', 'x'],
                 vec!['E', 'D', 'A'],
                 vec!['F', 'C', 'B'],
-            ],
+            ]),
         };
 
         let tile2 = Tile {
             id: 0,
-            tile: vec![
+            tile: Grid::from_rows(vec![
                 vec!['1', 'b', 'u'],
                 vec!['2', 'd', 'v'],
                 vec!['x', 'x', 'x'],
-            ],
+            ]),
         };
 
         assert!(tile1.vfit(&tile2));
     }
 
+    #[test]
+    fn test_parse_invalid() {
+        let error = |s: &str| input(&Input::from(s)).err().unwrap();
+
+        assert_eq!(error("Tile 1:\n.#\n#.\n\nTile 2\n.#\n#.").line(), Some(5));
+        assert_eq!(error("Tile 1:\n.#\n#.\n\nTile 2:\n.#\n#").line(), Some(7));
+        assert_eq!(error("Tile 1:\n.#\n#x").text(), "#x");
+        assert_eq!(error("Tile 1:").reason(), "empty tile");
+    }
+
     #[test]
     fn test_pack() {
         assert_eq!(pack(&to_vec("..#..#..##"), '#'), 0b010010011);
@@ -622,13 +567,11 @@ mod tests {
 
     #[test]
     fn test_pattern() {
-        let pattern = vec![
+        let pattern = Grid::parse(vec![
             "                  # ",
             "#    ##    ##    ###",
             " #  #  #  #  #  #   ",
-        ].into_iter()
-            .map(|row| row.chars().collect())
-            .collect();
+        ], Some).unwrap();
 
         let tile = Tile { id: 0, tile: part2() };
 
@@ -642,152 +585,38 @@ mod tests {
 
     #[test]
     fn test_pattern_tiny() {
-        let pattern = vec![
+        let pattern = Grid::parse(vec![
             "                  # ",
             "#    ##    ##    ###",
             " #  #  #  #  #  #   ",
-        ].into_iter()
-            .map(|row| row.chars().collect())
-            .collect();
+        ], Some).unwrap();
 
-        let tile = vec![
+        let tile = Grid::parse(vec![
             "                  # ",
             "                  # ",
             "#....##....##....###",
             " #  #  #  #  #  #   ",
             " #  #  #  #  #  #   ",
-        ].into_iter()
-            .map(|row| row.chars().collect())
-            .collect();
+        ], Some).unwrap();
 
         assert_eq!(lookup(&tile, &pattern), 1);
     }
 
     #[test]
     fn test_pattern_trivial() {
-        let pattern = vec![
+        let pattern = Grid::parse(vec![
             "                  # ",
             "#    ##    ##    ###",
             " #  #  #  #  #  #   ",
-        ].into_iter()
-            .map(|row| row.chars().collect())
-            .collect();
+        ], Some).unwrap();
         assert_eq!(lookup(&pattern, &pattern), 1);
     }
 
     fn part1() -> Vec<Tile> {
-        let lines =
-r#"Tile 2311:
-..##.#..#.
-##..#.....
-#...##..#.
-####.#...#
-##.##.###.
-##...#.###
-.#.#.#..##
-..#....#..
-###...#.#.
-..###..###
-
-Tile 1951:
-#.##...##.
-#.####...#
-.....#..##
-#...######
-.##.#....#
-.###.#####
-###.##.##.
-.###....#.
-..#.#..#.#
-#...##.#..
-
-Tile 1171:
-####...##.
-#..##.#..#
-##.#..#.#.
-.###.####.
-..###.####
-.##....##.
-.#...####.
-#.##.####.
-####..#...
-.....##...
-
-Tile 1427:
-###.##.#..
-.#..#.##..
-.#.##.#..#
-#.#.#.##.#
-....#...##
-...##..##.
-...#.#####
-.#.####.#.
-..#..###.#
-..##.#..#.
-
-Tile 1489:
-##.#.#....
-..##...#..
-.##..##...
-..#...#...
-#####...#.
-#..#.#.#.#
-...#.#.#..
-##.#...##.
-..##.##.##
-###.##.#..
-
-Tile 2473:
-#....####.
-#..#.##...
-#.##..#...
-######.#.#
-.#...#.#.#
-.#########
-.###.#..#.
-########.#
-##...##.#.
-..###.#.#.
-
-Tile 2971:
-..#.#....#
-#...###...
-#.#.###...
-##.##..#..
-.#####..##
-.#..####.#
-#..#.#..#.
-..####.###
-..#.#.###.
-...#.#.#.#
-
-Tile 2729:
-...#.#.#.#
-####.#....
-..#.#.....
-....#..#.#
-.##..##.#.
-.#.####...
-####.#.#..
-##.####...
-##..#.##..
-#.##...##.
-
-Tile 3079:
-#.#.#####.
-.#..######
-..#.......
-######....
-####.#..#.
-.#...#.##.
-#.#####.##
-..#.###...
-..#.......
-..#.###..."#;
-        input(lines.lines().map(|s| s.to_string()).collect())
-    }
-
-    fn part2() -> Vec<Vec<char>> {
+        input(&Input::from(include_str!("../inputs/examples/day20-example.txt"))).unwrap()
+    }
+
+    fn part2() -> Grid<char> {
         let raw =
 r#".#.#..#.##...#.##..#####
 ###....#.#....#..#......
@@ -813,6 +642,6 @@ r#".#.#..#.##...#.##..#####
 #.##..#..#...#..####...#
 .#.###..##..##..####.##.
 ...###...##...#...#..###"#;
-        raw.split("\n").into_iter().map(|row| row.chars().collect()).collect()
+        Grid::parse(raw.lines(), Some).unwrap()
     }
 }
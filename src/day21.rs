@@ -1,39 +1,43 @@
-use crate::utils::lines;
+#![allow(clippy::into_iter_on_ref, clippy::ptr_arg, clippy::redundant_closure, clippy::useless_vec)]
+
+use crate::error::{parse_lines, Error, Result};
+use crate::solution::Solution;
 use std::collections::{HashSet, HashMap};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-struct Food {
+pub struct Food {
     ingredients: Vec<String>,
     allergens: Vec<String>,
 }
 
-fn parse(line: &str) -> Food {
-    let ing = line.chars()
-        .take_while(|c| *c != '(')
-        .collect::<String>();
-
-    let ingredients = ing.split(" ")
-        .into_iter()
+fn parse(line: &str) -> Result<Food> {
+    let (ing, alg) = match line.split_once('(') {
+        Some((ing, rest)) => {
+            let alg = rest.strip_prefix("contains ")
+                .and_then(|s| s.strip_suffix(')'))
+                .ok_or_else(|| Error::new(line, "expected `(contains ...)`"))?;
+            (ing, alg)
+        },
+        None => (line, ""),
+    };
+
+    let ingredients: Vec<String> = ing.split(' ')
         .filter(|w| !w.is_empty())
         .map(|s| s.to_string())
         .collect();
-
-    let alg = line.chars()
-        .skip_while(|c| *c != '(')
-        .skip("(contains ".len())
-        .take_while(|c| *c != ')')
-        .collect::<String>();
+    if ingredients.is_empty() {
+        return Err(Error::new(line, "no ingredients"));
+    }
 
     let allergens = alg.split(", ")
-        .into_iter()
         .filter(|w| !w.is_empty())
         .map(|s| s.to_string())
         .collect();
 
-    Food {
+    Ok(Food {
         ingredients,
         allergens,
-    }
+    })
 }
 
 fn collect<F>(foods: &Vec<Food>, f: F) -> HashSet<String>
@@ -52,33 +56,46 @@ fn count(ingredient: &String, foods: &Vec<Food>) -> usize {
         .count()
 }
 
-pub fn main() {
-    let lines = lines();
-    let foods = lines.iter()
-        .map(|line| parse(line))
-        .collect::<Vec<_>>();
-
-    let ingredients = collect(&foods, |f| &f.ingredients)
+// Possible ingredients for each allergen, and ingredients that can't contain any allergen.
+fn analyze(foods: &Vec<Food>) -> (HashMap<String, HashSet<String>>, HashSet<String>) {
+    let ingredients = collect(foods, |f| &f.ingredients)
         .into_iter().collect::<Vec<_>>();
-    let allergens = collect(&foods, |f| &f.allergens)
+    let allergens = collect(foods, |f| &f.allergens)
         .into_iter().collect::<Vec<_>>();
 
-    let a = a(&foods, &allergens);
+    let a = a(foods, &allergens);
     let b = b(&a);
     let c = c(&b, &ingredients);
+    (a, c)
+}
 
-    let n = c.iter()
-        .map(|ing| count(ing, &foods))
-        .sum::<usize>();
-    println!("{}", n); // 2595
+pub struct Day21;
 
-    let d = d(&a, &c);
-    let list = d.iter()
-        .map(|(i, _)| i)
-        .cloned()
-        .collect::<Vec<_>>()
-        .join(",");
-    println!("{}", list);
+impl Solution for Day21 {
+    type Input = Vec<Food>;
+    type Part1 = usize;
+    type Part2 = String;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        parse_lines(text.lines(), parse)
+    }
+
+    fn part1(foods: &Self::Input) -> usize {
+        let (_, c) = analyze(foods);
+        c.iter()
+            .map(|ing| count(ing, foods))
+            .sum::<usize>()
+    }
+
+    fn part2(foods: &Self::Input) -> String {
+        let (a, c) = analyze(foods);
+        let d = d(&a, &c);
+        d.iter()
+            .map(|(i, _)| i)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(",")
+    }
 }
 
 fn a(foods: &Vec<Food>, allergens: &Vec<String>) -> HashMap<String, HashSet<String>> {
@@ -157,26 +174,30 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        assert_eq!(parse("mxmxvkd kfcds sqjhc nhms (contains dairy, fish)"), Food {
+        assert_eq!(parse("mxmxvkd kfcds sqjhc nhms (contains dairy, fish)").unwrap(), Food {
             ingredients: vec!["mxmxvkd", "kfcds", "sqjhc", "nhms"]
                 .iter().map(|s| s.to_string()).collect(),
             allergens: vec!["dairy", "fish"]
                 .iter().map(|s| s.to_string()).collect(),
         });
 
-        assert_eq!(parse("trh fvjkl sbzzf mxmxvkd (contains dairy)"), Food {
+        assert_eq!(parse("trh fvjkl sbzzf mxmxvkd (contains dairy)").unwrap(), Food {
             ingredients: vec!["trh", "fvjkl", "sbzzf", "mxmxvkd"]
                 .iter().map(|s| s.to_string()).collect(),
             allergens: vec!["dairy"]
                 .iter().map(|s| s.to_string()).collect(),
         });
+
+        assert!(parse("(contains dairy)").is_err());
+        assert!(parse("trh fvjkl (dairy)").is_err());
+        assert!(parse("trh fvjkl (contains dairy").is_err());
     }
 
     #[test]
     fn test_c() {
         let (lines, expected, _) = example();
         let foods = lines.into_iter()
-            .map(|line| parse(&line))
+            .map(|line| parse(&line).unwrap())
             .collect::<Vec<_>>();
 
         let ingredients = collect(&foods, |f| &f.ingredients)
@@ -195,7 +216,7 @@ mod tests {
     fn test_d() {
         let (lines, _, expected) = example();
         let foods = lines.into_iter()
-            .map(|line| parse(&line))
+            .map(|line| parse(&line).unwrap())
             .collect::<Vec<_>>();
 
         let ingredients = collect(&foods, |f| &f.ingredients)
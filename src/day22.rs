@@ -1,21 +1,30 @@
-use crate::utils::lines;
+#![allow(clippy::get_first, clippy::into_iter_on_ref)]
+
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
+use crate::input::Input;
 use std::collections::{VecDeque, HashSet};
 
 type Card = usize;
 
-fn parse(lines: &Vec<String>) -> (VecDeque<Card>, VecDeque<Card>) {
-    let mut it = lines.split(|s| s.is_empty());
-    let a = it.next().unwrap().into_iter()
-        .skip(1)
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect();
+fn deck(section: Option<&(usize, &[String])>, header: &str) -> Result<VecDeque<Card>> {
+    let (start, lines) = section
+        .ok_or_else(|| Error::new("", format!("missing `{}` deck", header)))?;
+    if lines.first().map(|s| s.as_str()) != Some(header) {
+        let line = lines.first().map(|s| s.as_str()).unwrap_or_default();
+        return Err(Error::new(line, format!("expected `{}`", header)).at(start + 1));
+    }
 
-    let b = it.next().unwrap().into_iter()
-        .skip(1)
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect();
+    let cards = parse_lines(lines.iter().skip(1).map(|s| s.as_str()), number::<Card>)
+        .map_err(|e| e.offset(start + 1))?;
+    Ok(cards.into_iter().collect())
+}
 
-    (a, b)
+fn parse(input: &Input) -> Result<(VecDeque<Card>, VecDeque<Card>)> {
+    let decks = input.paragraphs();
+    let a = deck(decks.first(), "Player 1:")?;
+    let b = deck(decks.get(1), "Player 2:")?;
+    Ok((a, b))
 }
 
 fn play(mut a: VecDeque<Card>, mut b: VecDeque<Card>) -> Vec<Vec<Card>> {
@@ -97,22 +106,43 @@ fn score(result: &Vec<Vec<Card>>) -> usize {
         .sum()
 }
 
-pub fn main() {
-    let (a, b) = parse(&lines());
+pub struct Day22;
 
-    let r = play(a.clone(), b.clone());
-    let s = score(&r);
-    println!("{}", s);
+impl Solution for Day22 {
+    type Input = (VecDeque<Card>, VecDeque<Card>);
+    type Part1 = usize;
+    type Part2 = usize;
 
-    let r = rplay(a.clone(), b.clone());
-    let s = score(&r);
-    println!("{}", s);
+    fn parse(text: &str) -> Result<Self::Input> {
+        parse(&Input::from(text))
+    }
+
+    fn part1((a, b): &Self::Input) -> usize {
+        let r = play(a.clone(), b.clone());
+        score(&r)
+    }
+
+    fn part2((a, b): &Self::Input) -> usize {
+        let r = rplay(a.clone(), b.clone());
+        score(&r)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse() {
+        let (a, b) = parse(&Input::from("Player 1:\n9\n2\n\nPlayer 2:\n5\n8\n")).unwrap();
+        assert_eq!(a, vec![9, 2]);
+        assert_eq!(b, vec![5, 8]);
+
+        let e = parse(&Input::from("Player 1:\n9\n2\n\nPlayer 2:\n5\nx\n")).unwrap_err();
+        assert_eq!(e.line(), Some(7));
+        assert_eq!(parse(&Input::from("Player 1:\n9\n")).unwrap_err().reason(), "missing `Player 2:` deck");
+    }
+
     #[test]
     fn test_example_part2() {
         let a: Vec<Card> = vec![9,2,6,3,1];
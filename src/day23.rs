@@ -1,35 +1,67 @@
-use crate::utils::lines;
-
-pub fn main() {
-    let cups = input(lines());
+#![allow(
+    clippy::char_lit_as_u8,
+    clippy::clone_on_copy,
+    clippy::get_first,
+    clippy::ptr_arg,
+    clippy::unnecessary_cast,
+    clippy::useless_conversion,
+    clippy::useless_vec,
+)]
+
+use crate::error::{Error, Result};
+use crate::solution::Solution;
+
+pub struct Day23;
+
+impl Solution for Day23 {
+    type Input = Vec<usize>;
+    type Part1 = String;
+    type Part2 = usize;
+
+    fn parse(text: &str) -> Result<Self::Input> {
+        input(text)
+    }
 
-    let state = (0..100).fold(cups.clone(), |cups, _| step(cups));
-    println!("{}", to_str(align(state)));
+    fn part1(cups: &Self::Input) -> String {
+        let state = (0..100).fold(cups.clone(), |cups, _| step(cups));
+        to_str(align(state))
+    }
 
+    fn part2(cups: &Self::Input) -> usize {
+        let n = 10_000_000;
+        let cups = unfold(cups.clone());
 
-    let n = 10_000_000;
-    let cups = unfold(cups);
+        let mut list = as_list(&cups);
+        let mut cup = cups[0];
 
-    let mut list = as_list(&cups);
-    let mut cup = cups[0];
+        for _ in 0..n {
+            step2(&mut list, &mut cup);
+        }
 
-    for _ in 0..n {
-        step2(&mut list, &mut cup);
+        let a = list[1];
+        let b = list[a];
+        a * b
     }
-
-    let a = list[1];
-    let b = list[a];
-    println!("{}", a * b);
 }
 
-fn parse(line: &str) -> Vec<usize> {
-    line.chars().into_iter()
-        .map(|c| c.to_digit(10).unwrap() as usize)
-        .collect()
+fn parse(line: &str) -> Result<Vec<usize>> {
+    let cups = line.chars()
+        .map(|c| c.to_digit(10).map(|d| d as usize))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(|| Error::new(line, "expected digits"))?;
+
+    // Cups must be labeled 1 to N, each label used exactly once
+    let mut labels = cups.clone();
+    labels.sort_unstable();
+    if labels.is_empty() || labels.into_iter().ne(1..=cups.len()) {
+        return Err(Error::new(line, format!("expected labels 1 to {}", cups.len().max(1))));
+    }
+    Ok(cups)
 }
 
-fn input(lines: Vec<String>) -> Vec<usize> {
-    parse(lines.iter().next().unwrap())
+fn input(text: &str) -> Result<Vec<usize>> {
+    parse(text.lines().next().unwrap_or_default())
+        .map_err(|e| e.at(1))
 }
 
 fn step(cups: Vec<usize>) -> Vec<usize> {
@@ -106,7 +138,7 @@ fn align(cups: Vec<usize>) -> Vec<usize> {
 fn to_str(cups: Vec<usize>) -> String {
     cups.into_iter()
         .skip(1)
-        .map(|x| ((x as u8 + '0' as u8) as char))
+        .map(|x| (x as u8 + '0' as u8) as char)
         .collect()
 }
 
@@ -173,7 +205,11 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        assert_eq!(parse("853192647"), vec![8, 5, 3, 1, 9, 2, 6, 4, 7]);
+        assert_eq!(parse("853192647"), Ok(vec![8, 5, 3, 1, 9, 2, 6, 4, 7]));
+
+        assert!(parse("85319264x").is_err());
+        assert!(parse("853192646").is_err());
+        assert!(parse("").is_err());
     }
 
     #[test]
@@ -1,4 +1,8 @@
-use crate::utils::lines;
+#![allow(clippy::into_iter_on_ref, clippy::redundant_guards, clippy::useless_conversion)]
+
+use crate::error::{parse_lines, Error, Result};
+use crate::solution::Solution;
+use crate::input::Input;
 use std::collections::{HashMap, HashSet};
 
 
@@ -13,7 +17,7 @@ enum Dir {
 }
 
 #[derive(Debug, Default, Eq, PartialEq, Hash, Clone)]
-struct Cell(i32, i32);
+pub struct Cell(i32, i32);
 
 impl Cell {
     fn zero() -> Self {
@@ -43,7 +47,7 @@ impl Cell {
     }
 }
 
-fn parse(line: &str) -> Vec<Dir> {
+fn parse(line: &str) -> Result<Vec<Dir>> {
     let mut it = line.chars().into_iter();
 
     let mut acc = Vec::new();
@@ -61,25 +65,27 @@ fn parse(line: &str) -> Vec<Dir> {
                 ('s', 'w') => acc.push(Dir::SW),
                 ('n', 'e') => acc.push(Dir::NE),
                 ('n', 'w') => acc.push(Dir::NW),
-                _ => unreachable!()
+                _ => return Err(Error::new(line, format!("unknown direction `{}{}`", p, c)))
             }
             p = 'X';
         } else {
             match c {
                 'e' => acc.push(Dir::E),
                 'w' => acc.push(Dir::W),
-                _ => p = c
+                's' | 'n' => p = c,
+                _ => return Err(Error::new(line, format!("unknown direction `{}`", c)))
             }
         }
     }
 
-    acc
+    if p != 'X' {
+        return Err(Error::new(line, format!("unknown direction `{}`", p)));
+    }
+    Ok(acc)
 }
 
-fn input(lines: &Vec<String>) -> Vec<Vec<Dir>> {
-    lines.into_iter()
-        .map(|line| parse(line))
-        .collect()
+fn input(lines: &[String]) -> Result<Vec<Vec<Dir>>> {
+    parse_lines(lines.iter().map(|line| line.as_str()), parse)
 }
 
 fn reduce(cell: Cell, path: &Vec<Dir>) -> Cell {
@@ -144,21 +150,34 @@ fn day(floor: &mut HashMap<Cell, usize>) {
 }
 
 
-pub fn main() {
-    let paths = input(&lines());
+pub struct Day24;
 
-    let mut floor: HashMap<Cell, usize> = HashMap::new();
-    paths.iter()
-        .for_each(|path| flip(&mut floor, path));
+impl Solution for Day24 {
+    type Input = HashMap<Cell, usize>;
+    type Part1 = usize;
+    type Part2 = usize;
 
-    let n = count(&floor);
-    println!("{}", n);
+    // Floor after flipping tiles along all paths from the input
+    fn parse(text: &str) -> Result<Self::Input> {
+        let paths = input(Input::from(text).lines())?;
 
-    for _ in 0..100 {
-        day(&mut floor);
+        let mut floor: HashMap<Cell, usize> = HashMap::new();
+        paths.iter()
+            .for_each(|path| flip(&mut floor, path));
+        Ok(floor)
+    }
+
+    fn part1(floor: &Self::Input) -> usize {
+        count(floor)
+    }
+
+    fn part2(floor: &Self::Input) -> usize {
+        let mut floor = floor.clone();
+        for _ in 0..100 {
+            day(&mut floor);
+        }
+        count(&floor)
     }
-    let n = count(&floor);
-    println!("{}", n);
 }
 
 #[cfg(test)]
@@ -168,7 +187,11 @@ mod tests {
 
     #[test]
     fn test_parse() {
-        assert_eq!(parse("nwwwewwewww"), vec![NW, W, W, E, W, W, E, W, W, W]);
+        assert_eq!(parse("nwwwewwewww"), Ok(vec![NW, W, W, E, W, W, E, W, W, W]));
+
+        assert_eq!(parse("nwwx").unwrap_err().reason(), "unknown direction `x`");
+        assert_eq!(parse("nsw").unwrap_err().reason(), "unknown direction `ns`");
+        assert_eq!(parse("wwn").unwrap_err().reason(), "unknown direction `n`");
     }
 
     #[test]
@@ -185,7 +208,7 @@ mod tests {
 
     #[test]
     fn test_step() {
-        let path = parse("nwwswee");
+        let path = parse("nwwswee").unwrap();
         let cell = reduce(Cell::zero(), &path);
         assert_eq!(cell, Cell::zero());
     }
@@ -208,31 +231,6 @@ mod tests {
     }
 
     fn example() -> Vec<Vec<Dir>> {
-        let raw = vec![
-            "sesenwnenenewseeswwswswwnenewsewsw",
-            "neeenesenwnwwswnenewnwwsewnenwseswesw",
-            "seswneswswsenwwnwse",
-            "nwnwneseeswswnenewneswwnewseswneseene",
-            "swweswneswnenwsewnwneneseenw",
-            "eesenwseswswnenwswnwnwsewwnwsene",
-            "sewnenenenesenwsewnenwwwse",
-            "wenwwweseeeweswwwnwwe",
-            "wsweesenenewnwwnwsenewsenwwsesesenwne",
-            "neeswseenwwswnwswswnw",
-            "nenwswwsewswnenenewsenwsenwnesesenew",
-            "enewnwewneswsewnwswenweswnenwsenwsw",
-            "sweneswneswneneenwnewenewwneswswnese",
-            "swwesenesewenwneswnwwneseswwne",
-            "enesenwswwswneneswsenwnewswseenwsese",
-            "vwnwnesenesenenwwnenwsewesewsesesew",
-            "nenewswnwewswnenesenwnesewesw",
-            "eneswnwswnwsenenwnwnwwseeswneewsenese",
-            "neswnwewnwnwseenwseesewsenwsweewe",
-            "wseweeenwnesenwwwswnew",
-        ];
-
-        raw.into_iter()
-            .map(|line| parse(line))
-            .collect()
+        input(Input::from(include_str!("../inputs/examples/day24-example.txt")).lines()).unwrap()
     }
 }
@@ -1,4 +1,5 @@
-use crate::utils::lines;
+use crate::error::{number, parse_lines, Error, Result};
+use crate::solution::Solution;
 
 
 const D: u64 = 20201227;
@@ -29,19 +30,42 @@ fn find_loop(subject: u64, key: u64) -> usize {
     count
 }
 
-pub fn main() {
-    let mut it = lines().into_iter();
-    let door_pk: u64 = it.next().unwrap().parse().unwrap();
-    let card_pk: u64 = it.next().unwrap().parse().unwrap();
+pub struct Day25;
 
-    let door_loop = find_loop(7, door_pk);
-    let card_loop = find_loop(7, card_pk);
+impl Solution for Day25 {
+    type Input = (u64, u64);
+    type Part1 = u64;
+    type Part2 = u64;
 
-    let secret1 = pk(door_pk,card_loop);
-    let secret2 = pk(card_pk, door_loop);
-    assert_eq!(secret1, secret2);
+    const PARTS: usize = 1;
 
-    println!("{}", secret1);
+    fn parse(text: &str) -> Result<Self::Input> {
+        let keys = parse_lines(text.lines(), |line| {
+            match number::<u64>(line)? {
+                key if key > 0 && key < D => Ok(key),
+                _ => Err(Error::new(line, format!("public key must be within 1..{}", D))),
+            }
+        })?;
+        match keys.as_slice() {
+            [door_pk, card_pk] => Ok((*door_pk, *card_pk)),
+            _ => Err(Error::new(text.trim(), "expected two public keys")),
+        }
+    }
+
+    fn part1((door_pk, card_pk): &Self::Input) -> u64 {
+        let door_loop = find_loop(7, *door_pk);
+        let card_loop = find_loop(7, *card_pk);
+
+        let secret1 = pk(*door_pk, card_loop);
+        let secret2 = pk(*card_pk, door_loop);
+        assert_eq!(secret1, secret2);
+        secret1
+    }
+
+    // There is no second puzzle on the last day, see `PARTS`.
+    fn part2(_: &Self::Input) -> u64 {
+        unreachable!()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,111 @@
+use std::fmt;
+
+/// Input parsing error: what went wrong with which text, and where (day and line) it happened.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Error {
+    day: Option<usize>,
+    line: Option<usize>,
+    text: String,
+    reason: String,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(text: &str, reason: impl Into<String>) -> Self {
+        Self {
+            day: None,
+            line: None,
+            text: text.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Attach the (1-based) line number, unless the error already has one.
+    pub fn at(mut self, line: usize) -> Self {
+        self.line = self.line.or(Some(line));
+        self
+    }
+
+    /// Shift the line number by given amount of lines (e.g. when parsing a paragraph).
+    pub fn offset(mut self, lines: usize) -> Self {
+        self.line = self.line.map(|line| line + lines);
+        self
+    }
+
+    pub fn on(mut self, day: usize) -> Self {
+        self.day = Some(day);
+        self
+    }
+
+    pub fn day(&self) -> Option<usize> {
+        self.day
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(day) = self.day {
+            write!(f, "day {:02}, ", day)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "{} in {:?}", self.reason, self.text)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parse `text` into a number, reporting the text itself if it is not a valid one.
+pub fn number<T: std::str::FromStr>(text: &str) -> Result<T> {
+    text.parse::<T>()
+        .map_err(|_| Error::new(text, "invalid number"))
+}
+
+/// Parse every line with `f`, attaching the (1-based) line number to the error.
+pub fn parse_lines<'a, T, I, F>(lines: I, f: F) -> Result<Vec<T>>
+    where
+        I: IntoIterator<Item = &'a str>,
+        F: Fn(&'a str) -> Result<T>
+{
+    lines.into_iter()
+        .enumerate()
+        .map(|(i, line)| f(line).map_err(|e| e.at(i + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let e = Error::new("jmp +x", "invalid number");
+        assert_eq!(e.to_string(), "invalid number in \"jmp +x\"");
+
+        let e = e.at(3).offset(10).on(8);
+        assert_eq!(e.to_string(), "day 08, line 13: invalid number in \"jmp +x\"");
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        assert_eq!(parse_lines("1\n2\n3".lines(), number::<u8>), Ok(vec![1, 2, 3]));
+
+        let e = parse_lines("1\n2\nx".lines(), number::<u8>).unwrap_err();
+        assert_eq!(e.line(), Some(3));
+        assert_eq!(e.text(), "x");
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::answers::{self, Answers, Outcome};
+use crate::input::Input;
+
+/// Directory with example inputs, relative to the crate root.
+pub const DIR: &str = "inputs/examples";
+
+/// Example input `dayNN-<name>.txt` with expected answers in `dayNN-<name>.answers` next to it.
+/// Answers use the same format as the answers file, parts without an answer are not solved.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Example {
+    pub day: usize,
+    pub name: String,
+    pub input: PathBuf,
+    pub answers: PathBuf,
+}
+
+// Day number from the file name `dayNN-<name>`.
+fn day_of(name: &str) -> Option<usize> {
+    let (day, rest) = name.strip_prefix("day")?.split_once('-')?;
+    if day.len() != 2 || rest.is_empty() {
+        return None;
+    }
+    day.parse().ok()
+}
+
+/// All examples in the directory ordered by name, files not following the naming convention are skipped.
+pub fn find<P: AsRef<Path>>(dir: P) -> io::Result<Vec<Example>> {
+    let mut examples = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("txt") {
+            continue;
+        }
+        let name = path.file_stem().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+        if let Some(day) = day_of(&name) {
+            examples.push(Example {
+                day,
+                name,
+                answers: path.with_extension("answers"),
+                input: path,
+            });
+        }
+    }
+    examples.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(examples)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    Input::from_path(path)
+        .map(|input| input.text())
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))
+}
+
+impl Example {
+    /// Solve every part with an expected answer, returns the number of parts that passed.
+    pub fn check(&self) -> Result<usize, String> {
+        let day = crate::day(self.day)
+            .ok_or_else(|| format!("{}: unknown day {}", self.name, self.day))?;
+        let text = read(&self.input)?;
+        let answers = Answers::parse(&read(&self.answers)?)
+            .map_err(|e| format!("{}: {}", self.answers.display(), e))?;
+
+        let mut passed = 0;
+        for (part, outcome) in answers::check(&day, Some(&text), &answers) {
+            match outcome {
+                Outcome::Pass => passed += 1,
+                Outcome::Fail(reason) => return Err(format!("{} part {}: {}", self.name, part, reason)),
+                Outcome::Missing(_) => (),
+            }
+        }
+        if passed == 0 {
+            return Err(format!("{}: no answers for day {:02}", self.name, self.day));
+        }
+        Ok(passed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_of() {
+        assert_eq!(day_of("day01-example"), Some(1));
+        assert_eq!(day_of("day20-larger-grid"), Some(20));
+        assert_eq!(day_of("day01"), None);
+        assert_eq!(day_of("day1-example"), None);
+        assert_eq!(day_of("day01-"), None);
+        assert_eq!(day_of("answers"), None);
+    }
+
+    #[test]
+    fn test_examples() {
+        let examples = find(Path::new(env!("CARGO_MANIFEST_DIR")).join(DIR)).unwrap();
+        assert!(!examples.is_empty());
+
+        let failures = examples.iter()
+            .filter_map(|example| example.check().err())
+            .collect::<Vec<_>>();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,332 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::error::{parse_lines, Error, Result};
+
+/// Position in the grid as (row, col).
+pub type Pos = (usize, usize);
+
+/// Direction as (row, col) delta.
+pub type Dir = (isize, isize);
+
+/// Up, left, right, down.
+pub const DIRS4: [Dir; 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/// All 8 directions, row by row: top-left to bottom-right.
+pub const DIRS8: [Dir; 8] = [
+    (-1, -1), (-1, 0), (-1, 1),
+    ( 0, -1),          ( 0, 1),
+    ( 1, -1), ( 1, 0), ( 1, 1),
+];
+
+/// Rectangular grid of cells, stored row by row.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Grid<T> {
+    rows: usize,
+    cols: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Build the grid from rows, all rows must have the same length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Self {
+        let cols = rows.first().map(|row| row.len()).unwrap_or_default();
+        assert!(rows.iter().all(|row| row.len() == cols), "rows must have the same length");
+        Self {
+            rows: rows.len(),
+            cols,
+            cells: rows.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Parse non-empty lines of equal length, converting each char with `cell`.
+    pub fn parse<'a, I, F>(lines: I, cell: F) -> Result<Self>
+        where
+            I: IntoIterator<Item = &'a str>,
+            F: Fn(char) -> Option<T>
+    {
+        let lines: Vec<&str> = lines.into_iter().collect();
+        let cols = match lines.first() {
+            Some(line) => line.chars().count(),
+            None => return Err(Error::new("", "empty grid")),
+        };
+
+        let rows = parse_lines(lines, |line| {
+            if line.is_empty() {
+                return Err(Error::new(line, "empty row"));
+            }
+            if line.chars().count() != cols {
+                return Err(Error::new(line, "rows must have the same length"));
+            }
+            line.chars()
+                .map(|c| cell(c).ok_or_else(|| Error::new(line, format!("unexpected character {:?}", c))))
+                .collect::<Result<Vec<T>>>()
+        })?;
+        Ok(Self::from_rows(rows))
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get(&self, (row, col): Pos) -> Option<&T> {
+        if row < self.rows && col < self.cols {
+            Some(&self.cells[row * self.cols + col])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, (row, col): Pos) -> Option<&mut T> {
+        if row < self.rows && col < self.cols {
+            Some(&mut self.cells[row * self.cols + col])
+        } else {
+            None
+        }
+    }
+
+    /// Set the cell, returns false if the position is out of the grid.
+    pub fn set(&mut self, pos: Pos, value: T) -> bool {
+        match self.get_mut(pos) {
+            Some(cell) => {
+                *cell = value;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Cell at given position, with the grid repeating itself infinitely in both directions.
+    pub fn wrapped(&self, (row, col): Pos) -> &T {
+        &self.cells[(row % self.rows) * self.cols + col % self.cols]
+    }
+
+    /// Move from the position in given direction, if the result is still in the grid.
+    pub fn step(&self, (row, col): Pos, (dr, dc): Dir) -> Option<Pos> {
+        let row = row.checked_add_signed(dr).filter(|r| *r < self.rows)?;
+        let col = col.checked_add_signed(dc).filter(|c| *c < self.cols)?;
+        Some((row, col))
+    }
+
+    pub fn neighbours4(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        DIRS4.iter().filter_map(move |dir| self.step(pos, *dir))
+    }
+
+    pub fn neighbours8(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        DIRS8.iter().filter_map(move |dir| self.step(pos, *dir))
+    }
+
+    /// All positions from given one (excluding it) in given direction until the edge of the grid.
+    pub fn ray(&self, pos: Pos, dir: Dir) -> impl Iterator<Item = Pos> + '_ {
+        assert_ne!(dir, (0, 0));
+        std::iter::successors(self.step(pos, dir), move |p| self.step(*p, dir))
+    }
+
+    /// First position in given direction with the cell matching the predicate.
+    pub fn cast<F: Fn(&T) -> bool>(&self, pos: Pos, dir: Dir, f: F) -> Option<Pos> {
+        self.ray(pos, dir).find(|p| f(&self[*p]))
+    }
+
+    /// All positions, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Pos> {
+        let cols = self.cols;
+        (0..self.rows * cols).map(move |i| (i / cols, i % cols))
+    }
+
+    /// All cells, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn col(&self, col: usize) -> impl Iterator<Item = &T> {
+        assert!(col < self.cols);
+        self.cells.iter().skip(col).step_by(self.cols)
+    }
+
+    pub fn map<U, F: Fn(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {
+            rows: self.rows,
+            cols: self.cols,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn filled(rows: usize, cols: usize, value: T) -> Self {
+        Self {
+            rows,
+            cols,
+            cells: vec![value; rows * cols],
+        }
+    }
+
+    fn build<F: Fn(Pos) -> Pos>(&self, rows: usize, cols: usize, from: F) -> Self {
+        let cells = (0..rows * cols)
+            .map(|i| self[from((i / cols, i % cols))].clone())
+            .collect();
+        Self { rows, cols, cells }
+    }
+
+    /// Rotate clockwise.
+    pub fn cw(&self) -> Self {
+        let n = self.rows;
+        self.build(self.cols, self.rows, |(r, c)| (n - 1 - c, r))
+    }
+
+    /// Rotate counter-clockwise.
+    pub fn ccw(&self) -> Self {
+        let n = self.cols;
+        self.build(self.cols, self.rows, |(r, c)| (c, n - 1 - r))
+    }
+
+    /// Mirror left to right (reverse every row).
+    pub fn vflip(&self) -> Self {
+        let n = self.cols;
+        self.build(self.rows, self.cols, |(r, c)| (r, n - 1 - c))
+    }
+
+    /// Mirror top to bottom (reverse order of rows).
+    pub fn hflip(&self) -> Self {
+        let n = self.rows;
+        self.build(self.rows, self.cols, |(r, c)| (n - 1 - r, c))
+    }
+
+    /// Sub-grid of given size with the top-left corner at given position.
+    pub fn crop(&self, (row, col): Pos, rows: usize, cols: usize) -> Self {
+        assert!(row + rows <= self.rows && col + cols <= self.cols);
+        self.build(rows, cols, |(r, c)| (row + r, col + c))
+    }
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        self.get(pos).expect("position out of the grid")
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        self.get_mut(pos).expect("position out of the grid")
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.rows {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            for cell in self.row(row) {
+                write!(f, "{}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(text: &str) -> Grid<char> {
+        Grid::parse(text.lines(), Some).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let g = grid("123\n456");
+        assert_eq!((g.rows(), g.cols()), (2, 3));
+        assert_eq!(g, Grid::from_rows(vec![vec!['1', '2', '3'], vec!['4', '5', '6']]));
+        assert_eq!(g.to_string(), "123\n456");
+
+        let binary = |c| match c {
+            '.' => Some(false),
+            '#' => Some(true),
+            _ => None,
+        };
+        assert_eq!(Grid::parse("#.\n.#".lines(), binary).unwrap().iter().filter(|x| **x).count(), 2);
+
+        let e = Grid::parse(".#\n.x".lines(), binary).unwrap_err();
+        assert_eq!((e.line(), e.text()), (Some(2), ".x"));
+        let e = Grid::parse(".#\n.".lines(), binary).unwrap_err();
+        assert_eq!((e.line(), e.reason()), (Some(2), "rows must have the same length"));
+        assert!(Grid::parse(".#\n\n.#".lines(), binary).is_err());
+        assert_eq!(Grid::parse("".lines(), binary).unwrap_err().reason(), "empty grid");
+    }
+
+    #[test]
+    fn test_get_set() {
+        let mut g = grid("123\n456");
+        assert_eq!(g.get((1, 2)), Some(&'6'));
+        assert_eq!(g.get((2, 0)), None);
+        assert_eq!(g.get((0, 3)), None);
+
+        assert!(g.set((0, 0), 'x'));
+        assert!(!g.set((0, 3), 'x'));
+        g[(1, 1)] = 'y';
+        assert_eq!(g.to_string(), "x23\n4y6");
+    }
+
+    #[test]
+    fn test_wrapped() {
+        let g = grid("123\n456");
+        assert_eq!(*g.wrapped((0, 4)), '2');
+        assert_eq!(*g.wrapped((3, 7)), '5');
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let g = grid("123\n456\n789");
+        let at = |ps: Vec<Pos>| ps.into_iter().map(|p| g[p]).collect::<String>();
+
+        assert_eq!(at(g.neighbours4((1, 1)).collect()), "2468");
+        assert_eq!(at(g.neighbours4((0, 0)).collect()), "24");
+        assert_eq!(at(g.neighbours8((1, 1)).collect()), "12346789");
+        assert_eq!(at(g.neighbours8((2, 2)).collect()), "568");
+    }
+
+    #[test]
+    fn test_ray() {
+        let g = grid("#...\n.L.#\n....\n...#");
+        assert_eq!(g.ray((1, 1), (0, 1)).collect::<Vec<_>>(), vec![(1, 2), (1, 3)]);
+        assert_eq!(g.ray((1, 1), (-1, -1)).collect::<Vec<_>>(), vec![(0, 0)]);
+        assert_eq!(g.ray((0, 0), (-1, 0)).count(), 0);
+
+        assert_eq!(g.cast((1, 1), (0, 1), |c| *c != '.'), Some((1, 3)));
+        assert_eq!(g.cast((1, 1), (1, 1), |c| *c != '.'), Some((3, 3)));
+        assert_eq!(g.cast((1, 1), (1, 0), |c| *c != '.'), None);
+    }
+
+    #[test]
+    fn test_transform() {
+        let g = grid("123\n456");
+        assert_eq!(g.cw().to_string(), "41\n52\n63");
+        assert_eq!(g.ccw().to_string(), "36\n25\n14");
+        assert_eq!(g.vflip().to_string(), "321\n654");
+        assert_eq!(g.hflip().to_string(), "456\n123");
+        assert_eq!(g.cw().cw().cw().cw(), g);
+        assert_eq!(g.cw().ccw(), g);
+        assert_eq!(g.crop((0, 1), 2, 2).to_string(), "23\n56");
+    }
+
+    #[test]
+    fn test_rows_cols() {
+        let g = grid("123\n456");
+        assert_eq!(g.row(1), &['4', '5', '6']);
+        assert_eq!(g.col(2).collect::<String>(), "36");
+        assert_eq!(g.positions().collect::<Vec<_>>(), vec![(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2)]);
+        assert_eq!(g.map(|c| c.to_digit(10).unwrap()).iter().sum::<u32>(), 21);
+        assert_eq!(Grid::filled(2, 2, 0).iter().count(), 4);
+    }
+}
@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::error::Result;
+
+/// Puzzle input as lines, without line endings (`\n` or `\r\n`) and trailing blank lines.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Input {
+    lines: Vec<String>,
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+impl Input {
+    fn of(mut lines: Vec<String>) -> Self {
+        lines.iter_mut()
+            .filter(|line| line.ends_with('\r'))
+            .for_each(|line| {
+                line.pop();
+            });
+        while lines.last().map(|line| is_blank(line)).unwrap_or_default() {
+            lines.pop();
+        }
+        Self { lines }
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>()?;
+        Ok(Self::of(lines))
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Normalized text: lines separated by `\n`, with a single trailing newline.
+    pub fn text(&self) -> String {
+        self.lines.iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Split lines into blank-line separated paragraphs, each with the index of its first line.
+    /// Several blank lines in a row do not produce empty paragraphs.
+    pub fn paragraphs(&self) -> Vec<(usize, &[String])> {
+        let mut result = Vec::new();
+        let mut start = 0;
+        for (i, line) in self.lines.iter().enumerate() {
+            if is_blank(line) {
+                if i > start {
+                    result.push((start, &self.lines[start..i]));
+                }
+                start = i + 1;
+            }
+        }
+        if start < self.lines.len() {
+            result.push((start, &self.lines[start..]));
+        }
+        result
+    }
+
+    /// Parse every paragraph with `f`, shifting line numbers of errors by the paragraph start.
+    pub fn parse_paragraphs<T, F>(&self, f: F) -> Result<Vec<T>>
+        where
+            F: Fn(&[String]) -> Result<T>
+    {
+        self.paragraphs().into_iter()
+            .map(|(start, lines)| f(lines).map_err(|e| e.offset(start)))
+            .collect()
+    }
+}
+
+impl From<&str> for Input {
+    fn from(text: &str) -> Self {
+        Self::of(text.lines().map(|line| line.to_string()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{number, parse_lines};
+
+    #[test]
+    fn test_from() {
+        let expected = vec!["a".to_string(), "".to_string(), "b".to_string()];
+
+        assert_eq!(Input::from("a\n\nb").lines(), expected.as_slice());
+        assert_eq!(Input::from("a\n\nb\n\n\n").lines(), expected.as_slice());
+        assert_eq!(Input::from("a\r\n\r\nb\r\n").lines(), expected.as_slice());
+        assert_eq!(Input::from_reader("a\r\n\r\nb\r\n \n".as_bytes()).unwrap().lines(), expected.as_slice());
+
+        assert_eq!(Input::from("a\r\nb").text(), "a\nb\n");
+        assert_eq!(Input::from("").lines().len(), 0);
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let input = Input::from("a\nb\n\nc\n\nd\ne");
+        let lines = input.lines();
+        assert_eq!(input.paragraphs(), vec![
+            (0, &lines[0..2]),
+            (3, &lines[3..4]),
+            (5, &lines[5..7]),
+        ]);
+
+        let input = Input::from("\na\n\n\n\nb\n");
+        let lines = input.lines();
+        assert_eq!(input.paragraphs(), vec![
+            (1, &lines[1..2]),
+            (5, &lines[5..6]),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_paragraphs() {
+        let input = Input::from("1\n2\n\n3\nx\n");
+        let sum = |lines: &[String]| parse_lines(lines.iter().map(|s| s.as_str()), number::<u32>)
+            .map(|ns| ns.into_iter().sum::<u32>());
+
+        assert_eq!(Input::from("1\n2\n\n3\n").parse_paragraphs(sum), Ok(vec![3, 3]));
+
+        let e = input.parse_paragraphs(sum).unwrap_err();
+        assert_eq!(e.line(), Some(5));
+        assert_eq!(e.text(), "x");
+    }
+}
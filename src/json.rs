@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::error::{Error, Result};
+
+/// Value of a flat JSON object: only unsigned integers and strings are supported.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    Number(u64),
+    String(String),
+}
+
+pub type Object = HashMap<String, Value>;
+
+/// Quote and escape the string as JSON string literal.
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    it: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&mut self, reason: &str) -> Error {
+        let pos = self.it.peek().map(|(i, _)| *i).unwrap_or(self.text.len());
+        let line = self.text[..pos].matches('\n').count() + 1;
+        let text = self.text.lines().nth(line - 1).unwrap_or_default();
+        Error::new(text, reason).at(line)
+    }
+
+    fn skip(&mut self) {
+        while self.it.peek().map(|(_, c)| c.is_whitespace()).unwrap_or_default() {
+            self.it.next();
+        }
+    }
+
+    // Skip whitespace and consume given char if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip();
+        if self.it.peek().map(|(_, x)| *x) == Some(c) {
+            self.it.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.it.next().map(|(_, c)| c) {
+                Some('"') => return Ok(out),
+                Some('\\') => match self.it.next().map(|(_, c)| c) {
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('/') => out.push('/'),
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.it.next().map(|(_, c)| c)).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok()
+                            .and_then(std::char::from_u32)
+                            .ok_or_else(|| self.error("invalid unicode escape"))?;
+                        out.push(c);
+                    },
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => out.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u64> {
+        let mut digits = String::new();
+        while let Some((_, c)) = self.it.peek().filter(|(_, c)| c.is_ascii_digit()) {
+            digits.push(*c);
+            self.it.next();
+        }
+        digits.parse().map_err(|_| self.error("expected a number or a string"))
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip();
+        match self.it.peek().map(|(_, c)| *c) {
+            Some('"') => self.string().map(Value::String),
+            _ => self.number().map(Value::Number),
+        }
+    }
+
+    fn object(&mut self) -> Result<Object> {
+        self.expect('{')?;
+        let mut object = Object::new();
+        if self.eat('}') {
+            return Ok(object);
+        }
+        loop {
+            self.skip();
+            let key = self.string()?;
+            self.expect(':')?;
+            let value = self.value()?;
+            object.insert(key, value);
+            if !self.eat(',') {
+                self.expect('}')?;
+                return Ok(object);
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Vec<Object>> {
+        self.expect('[')?;
+        let mut objects = Vec::new();
+        if self.eat(']') {
+            return Ok(objects);
+        }
+        loop {
+            objects.push(self.object()?);
+            if !self.eat(',') {
+                self.expect(']')?;
+                return Ok(objects);
+            }
+        }
+    }
+}
+
+/// Parse JSON array of flat objects (as written by this crate, e.g. saved benchmark results).
+pub fn parse_objects(text: &str) -> Result<Vec<Object>> {
+    let mut parser = Parser { text, it: text.char_indices().peekable() };
+    let objects = parser.array()?;
+    parser.skip();
+    if parser.it.peek().is_some() {
+        return Err(parser.error("unexpected trailing text"));
+    }
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("abc"), "\"abc\"");
+        assert_eq!(quote("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+
+    #[test]
+    fn test_parse_objects() {
+        let text = r#"[
+            {"day": 1, "stage": "parse"},
+            {"answer": "a,\"b\"!", "empty": ""},
+            {}
+        ]"#;
+        let objects = parse_objects(text).unwrap();
+
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[0].get("day"), Some(&Value::Number(1)));
+        assert_eq!(objects[0].get("stage"), Some(&Value::String("parse".to_string())));
+        assert_eq!(objects[1].get("answer"), Some(&Value::String("a,\"b\"!".to_string())));
+        assert_eq!(objects[1].get("empty"), Some(&Value::String("".to_string())));
+        assert!(objects[2].is_empty());
+
+        assert_eq!(parse_objects(&format!("[{{\"s\": {}}}]", quote("x\ty"))).unwrap()[0].get("s"),
+                   Some(&Value::String("x\ty".to_string())));
+    }
+
+    #[test]
+    fn test_parse_objects_invalid() {
+        let e = parse_objects("[\n{\"day\": 1},\n{\"day\": x}\n]").unwrap_err();
+        assert_eq!(e.line(), Some(3));
+
+        assert!(parse_objects("").is_err());
+        assert!(parse_objects("[{\"day\": 1}").is_err());
+        assert!(parse_objects("[{\"day\": \"1}]").is_err());
+        assert!(parse_objects("[] []").is_err());
+    }
+}
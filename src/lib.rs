@@ -2,6 +2,20 @@
 
 mod utils;
 
+pub mod answers;
+pub mod automaton;
+pub mod bench;
+pub mod bignum;
+pub mod error;
+pub mod examples;
+pub mod grid;
+pub mod input;
+pub mod json;
+pub mod runner;
+pub mod solution;
+
+use solution::Day;
+
 pub mod day01;
 pub mod day02;
 pub mod day03;
@@ -27,3 +41,40 @@ pub mod day22;
 pub mod day23;
 pub mod day24;
 pub mod day25;
+
+/// All days, ordered by the day number.
+pub fn days() -> Vec<Day> {
+    vec![
+        Day::of::<day01::Day01>(1),
+        Day::of::<day02::Day02>(2),
+        Day::of::<day03::Day03>(3),
+        Day::of::<day04::Day04>(4),
+        Day::of::<day05::Day05>(5),
+        Day::of::<day06::Day06>(6),
+        Day::of::<day07::Day07>(7),
+        Day::of::<day08::Day08>(8),
+        Day::of::<day09::Day09>(9),
+        Day::of::<day10::Day10>(10),
+        Day::of::<day11::Day11>(11),
+        Day::of::<day12::Day12>(12),
+        Day::of::<day13::Day13>(13),
+        Day::of::<day14::Day14>(14),
+        Day::of::<day15::Day15>(15),
+        Day::of::<day16::Day16>(16),
+        Day::of::<day17::Day17>(17),
+        Day::of::<day18::Day18>(18),
+        Day::of::<day19::Day19>(19),
+        Day::of::<day20::Day20>(20),
+        Day::of::<day21::Day21>(21),
+        Day::of::<day22::Day22>(22),
+        Day::of::<day23::Day23>(23),
+        Day::of::<day24::Day24>(24),
+        Day::of::<day25::Day25>(25),
+    ]
+}
+
+/// Look up the day by its number (1 to 25).
+pub fn day(number: usize) -> Option<Day> {
+    days().into_iter()
+        .find(|day| day.number() == number)
+}
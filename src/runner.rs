@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+use std::sync::{mpsc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+use crate::json;
+use crate::solution::{Day, Parsed};
+
+/// Answer to one part of the day, with the time it took to solve it.
+/// Answers are strings: most are numbers, but e.g. day 21 answers with a list of ingredients.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Answer {
+    pub day: usize,
+    pub part: usize,
+    pub answer: String,
+    pub elapsed: Duration,
+}
+
+fn timed<T, F: FnOnce() -> T>(f: F) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+/// Parse the input and solve given parts of the day, returns time of parsing and the answers.
+pub fn run(day: &Day, text: &str, parts: &[usize]) -> Result<(Duration, Vec<Answer>)> {
+    let (input, parse) = timed(|| day.parse(text));
+    let input = input?;
+
+    let answers = parts.iter()
+        .map(|p| {
+            let (answer, elapsed) = timed(|| day.solve(*p, &input).unwrap());
+            Answer { day: day.number(), part: *p, answer, elapsed }
+        })
+        .collect();
+    Ok((parse, answers))
+}
+
+/// Day with its input text and parts to solve, for running on the thread pool.
+pub struct Job {
+    pub day: Day,
+    pub text: String,
+    pub parts: Vec<usize>,
+}
+
+enum Task {
+    Parse(usize),
+    Solve(usize, usize),
+}
+
+enum Event {
+    Parsed(usize, Result<Duration>),
+    Solved(usize, Answer),
+}
+
+struct Queue {
+    tasks: VecDeque<Task>,
+    pending: usize,
+}
+
+// Marks the task as done when dropped, queueing tasks that follow from it. If solving panicked,
+// the queue is drained instead, so that other workers stop rather than wait forever.
+struct Done<'a> {
+    queue: &'a Mutex<Queue>,
+    ready: &'a Condvar,
+    next: Vec<Task>,
+}
+
+impl Drop for Done<'_> {
+    fn drop(&mut self) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if thread::panicking() {
+            queue.tasks.clear();
+            queue.pending = 0;
+        } else {
+            queue.pending += self.next.len();
+            queue.pending -= 1;
+            queue.tasks.extend(self.next.drain(..));
+        }
+        self.ready.notify_all();
+    }
+}
+
+fn next(queue: &Mutex<Queue>, ready: &Condvar) -> Option<Task> {
+    let mut queue = queue.lock().unwrap();
+    loop {
+        if let Some(task) = queue.tasks.pop_front() {
+            return Some(task);
+        }
+        if queue.pending == 0 {
+            return None;
+        }
+        queue = ready.wait(queue).unwrap();
+    }
+}
+
+/// Same as `run` for every job, but on given number of worker threads: each day is parsed
+/// as a separate task, and then each of its parts is solved as a separate task.
+/// Results are in the order of jobs, with answers in the order of parts.
+pub fn run_all(jobs: &[Job], threads: usize) -> Vec<Result<(Duration, Vec<Answer>)>> {
+    assert!(threads > 0);
+    let queue = Mutex::new(Queue {
+        tasks: (0..jobs.len()).map(Task::Parse).collect(),
+        pending: jobs.len(),
+    });
+    let ready = Condvar::new();
+    let inputs: Vec<OnceLock<Parsed>> = jobs.iter().map(|_| OnceLock::new()).collect();
+    let (tx, rx) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let (queue, ready, inputs, tx) = (&queue, &ready, &inputs, tx.clone());
+            scope.spawn(move || {
+                while let Some(task) = next(queue, ready) {
+                    let mut done = Done { queue, ready, next: Vec::new() };
+                    match task {
+                        Task::Parse(i) => {
+                            let job = &jobs[i];
+                            let (input, elapsed) = timed(|| job.day.parse(&job.text));
+                            let parsed = input.map(|input| {
+                                let _ = inputs[i].set(input);
+                                done.next = job.parts.iter().map(|p| Task::Solve(i, *p)).collect();
+                                elapsed
+                            });
+                            tx.send(Event::Parsed(i, parsed)).unwrap();
+                        },
+                        Task::Solve(i, p) => {
+                            let day = &jobs[i].day;
+                            let input = inputs[i].get().unwrap();
+                            let (answer, elapsed) = timed(|| day.solve(p, input).unwrap());
+                            tx.send(Event::Solved(i, Answer { day: day.number(), part: p, answer, elapsed })).unwrap();
+                        },
+                    }
+                }
+            });
+        }
+    });
+    drop(tx);
+
+    let mut results: Vec<Result<(Duration, Vec<Answer>)>> = jobs.iter()
+        .map(|_| Err(Error::new("", "not parsed")))
+        .collect();
+    let mut answers: Vec<Vec<Answer>> = jobs.iter().map(|_| Vec::new()).collect();
+    for event in rx {
+        match event {
+            Event::Parsed(i, parsed) => results[i] = parsed.map(|elapsed| (elapsed, Vec::new())),
+            Event::Solved(i, answer) => answers[i].push(answer),
+        }
+    }
+    for ((result, mut answers), job) in results.iter_mut().zip(answers).zip(jobs) {
+        if let Ok((_, solved)) = result {
+            answers.sort_by_key(|a| job.parts.iter().position(|p| *p == a.part));
+            *solved = answers;
+        }
+    }
+    results
+}
+
+/// JSON array of `{day, part, answer, elapsed_ns}` objects, with the answer always as a string.
+pub fn to_json(answers: &[Answer]) -> String {
+    let rows = answers.iter()
+        .map(|a| format!("  {{\"day\": {}, \"part\": {}, \"answer\": {}, \"elapsed_ns\": {}}}",
+                         a.day, a.part, json::quote(&a.answer), a.elapsed.as_nanos()))
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day01::Day01;
+    use crate::json::Value;
+
+    fn answer(day: usize, part: usize, answer: &str) -> Answer {
+        Answer { day, part, answer: answer.to_string(), elapsed: Duration::from_nanos(100) }
+    }
+
+    #[test]
+    fn test_run() {
+        let day = Day::of::<Day01>(1);
+        let (_, answers) = run(&day, "1721\n979\n366\n299\n675\n1456\n", &[2]).unwrap();
+        assert_eq!(answers.len(), 1);
+        assert_eq!((answers[0].day, answers[0].part, answers[0].answer.as_str()), (1, 2, "241861950"));
+
+        assert!(run(&day, "x", &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_run_all() {
+        let day = Day::of::<Day01>(1);
+        let text = "1721\n979\n366\n299\n675\n1456\n";
+        let jobs = vec![
+            Job { day, text: text.to_string(), parts: vec![2, 1] },
+            Job { day, text: "x".to_string(), parts: vec![1] },
+            Job { day, text: text.to_string(), parts: vec![1] },
+        ];
+
+        for threads in 1..=4 {
+            let results = run_all(&jobs, threads);
+            assert_eq!(results.len(), 3);
+
+            let answers = |i: usize| results[i].as_ref().unwrap().1.iter()
+                .map(|a| (a.part, a.answer.as_str()))
+                .collect::<Vec<_>>();
+            assert_eq!(answers(0), vec![(2, "241861950"), (1, "514579")]);
+            assert!(results[1].is_err());
+            assert_eq!(answers(2), vec![(1, "514579")]);
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let answers = vec![
+            answer(1, 1, "514579"),
+            answer(21, 2, "mxmxvkd,sqjhc,fvjkl"),
+            answer(23, 1, "67384529"),
+        ];
+        let objects = json::parse_objects(&to_json(&answers)).unwrap();
+
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[0].get("day"), Some(&Value::Number(1)));
+        assert_eq!(objects[0].get("part"), Some(&Value::Number(1)));
+        assert_eq!(objects[0].get("answer"), Some(&Value::String("514579".to_string())));
+        assert_eq!(objects[0].get("elapsed_ns"), Some(&Value::Number(100)));
+        assert_eq!(objects[1].get("answer"), Some(&Value::String("mxmxvkd,sqjhc,fvjkl".to_string())));
+        assert_eq!(objects[2].get("answer"), Some(&Value::String("67384529".to_string())));
+    }
+}
@@ -0,0 +1,119 @@
+use std::any::Any;
+use std::fmt::Display;
+
+use crate::error::Result;
+
+/// Puzzle solver: parse the input once, then compute answers for both parts from it.
+pub trait Solution {
+    type Input: Send + Sync + 'static;
+    type Part1: Display;
+    type Part2: Display;
+
+    /// Number of parts in the puzzle (day 25 has only the first one).
+    const PARTS: usize = 2;
+
+    fn parse(input: &str) -> Result<Self::Input>;
+
+    fn part1(input: &Self::Input) -> Self::Part1;
+
+    fn part2(input: &Self::Input) -> Self::Part2;
+}
+
+/// Parsed input of any day, as returned by `Day::parse`.
+pub type Parsed = Box<dyn Any + Send + Sync>;
+
+/// Type-erased `Solution` for a specific day, so that days can be looked up by number.
+#[derive(Clone, Copy)]
+pub struct Day {
+    number: usize,
+    parts: usize,
+    parse: fn(&str) -> Result<Parsed>,
+    part1: fn(&Parsed) -> String,
+    part2: fn(&Parsed) -> String,
+}
+
+impl Day {
+    pub fn of<S: Solution>(number: usize) -> Self {
+        Self {
+            number,
+            parts: S::PARTS,
+            parse: parse::<S>,
+            part1: part1::<S>,
+            part2: part2::<S>,
+        }
+    }
+
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    pub fn parts(&self) -> usize {
+        self.parts
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Parsed> {
+        (self.parse)(input)
+            .map_err(|e| e.on(self.number))
+    }
+
+    /// Solve given part (1 or 2) for parsed input, `None` if the day has no such part.
+    pub fn solve(&self, part: usize, input: &Parsed) -> Option<String> {
+        match part {
+            1 if self.parts >= 1 => Some((self.part1)(input)),
+            2 if self.parts >= 2 => Some((self.part2)(input)),
+            _ => None
+        }
+    }
+}
+
+fn parse<S: Solution>(input: &str) -> Result<Parsed> {
+    let parsed = S::parse(input)?;
+    Ok(Box::new(parsed))
+}
+
+fn part1<S: Solution>(input: &Parsed) -> String {
+    let input = input.downcast_ref::<S::Input>().unwrap();
+    S::part1(input).to_string()
+}
+
+fn part2<S: Solution>(input: &Parsed) -> String {
+    let input = input.downcast_ref::<S::Input>().unwrap();
+    S::part2(input).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day01::Day01;
+
+    #[test]
+    fn test_day() {
+        let day = Day::of::<Day01>(1);
+        let input = day.parse("1721\n979\n366\n299\n675\n1456\n").unwrap();
+
+        assert_eq!(day.solve(1, &input), Some("514579".to_string()));
+        assert_eq!(day.solve(2, &input), Some("241861950".to_string()));
+        assert_eq!(day.solve(3, &input), None);
+    }
+
+    #[test]
+    fn test_day_error() {
+        let day = Day::of::<Day01>(1);
+        let e = day.parse("1721\n979\nabc\n").err().unwrap();
+
+        assert_eq!(e.day(), Some(1));
+        assert_eq!(e.line(), Some(3));
+        assert_eq!(e.text(), "abc");
+    }
+
+    #[test]
+    fn test_registry() {
+        let days = crate::days();
+        assert_eq!(days.len(), 25);
+        assert!(days.iter().enumerate().all(|(i, day)| day.number() == i + 1));
+
+        assert_eq!(crate::day(1).map(|day| day.parts()), Some(2));
+        assert_eq!(crate::day(25).map(|day| day.parts()), Some(1));
+        assert!(crate::day(26).is_none());
+    }
+}
@@ -1,11 +1,4 @@
-use std::io::{self, BufRead};
-
-pub fn lines() -> Vec<String> {
-    let stdin = io::stdin();
-    stdin.lock().lines()
-        .map(|line| line.unwrap())
-        .collect()
-}
+#![allow(clippy::manual_is_multiple_of, clippy::never_loop)]
 
 #[allow(dead_code)]
 pub fn primes(mut x: u64) -> Vec<u64> {